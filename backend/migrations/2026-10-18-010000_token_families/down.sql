@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX `idx_tokens_family_id` ON `tokens`;
+ALTER TABLE `tokens`
+  DROP COLUMN `token_type`,
+  DROP COLUMN `family_id`;
@@ -0,0 +1,7 @@
+ALTER TABLE `tokens`
+  ADD COLUMN `token_type` VARCHAR(20) NOT NULL DEFAULT 'access',
+  ADD COLUMN `family_id` VARCHAR(36);
+
+UPDATE `tokens` SET `family_id` = UUID() WHERE `family_id` IS NULL;
+
+CREATE INDEX `idx_tokens_family_id` ON `tokens` (`family_id`);
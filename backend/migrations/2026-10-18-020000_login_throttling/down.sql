@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS login_attempts;
+ALTER TABLE `users`
+  DROP COLUMN `failed_login_count`,
+  DROP COLUMN `locked_until`;
@@ -0,0 +1,14 @@
+ALTER TABLE `users`
+  ADD COLUMN `failed_login_count` INT NOT NULL DEFAULT 0,
+  ADD COLUMN `locked_until` DATETIME;
+
+CREATE TABLE IF NOT EXISTS `login_attempts` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT,
+  `identifier` VARCHAR(255) NOT NULL,
+  `ip_address` VARCHAR(45),
+  `succeeded` BOOLEAN NOT NULL DEFAULT false,
+  `attempted_at` DATETIME NOT NULL DEFAULT (CURRENT_TIMESTAMP)
+);
+
+CREATE INDEX `idx_login_attempts_ip` ON `login_attempts` (`ip_address`, `attempted_at`);
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS `user_mfa` (
+  `user_id` INT PRIMARY KEY,
+  `secret` VARCHAR(64) NOT NULL,
+  `enabled` BOOLEAN NOT NULL DEFAULT false,
+  `created_at` DATETIME DEFAULT (CURRENT_TIMESTAMP),
+  `confirmed_at` DATETIME,
+  `last_used_step` BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS `mfa_recovery_codes` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT NOT NULL,
+  `code_hash` VARCHAR(255) NOT NULL,
+  `used_at` DATETIME
+);
+
+CREATE INDEX `idx_mfa_recovery_codes_user_id` ON `mfa_recovery_codes` (`user_id`);
+
+ALTER TABLE `roles` ADD COLUMN `require_mfa` BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,16 @@
+ALTER TABLE `users` ADD COLUMN `email_verified` BOOLEAN NOT NULL DEFAULT false;
+-- accounts created before verification existed keep working
+UPDATE `users` SET `email_verified` = true;
+
+-- single-use tokens sent by email (password reset, email verification), only their hash is stored
+CREATE TABLE IF NOT EXISTS `user_action_tokens` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT NOT NULL,
+  `token_hash` VARCHAR(64) NOT NULL UNIQUE,
+  `purpose` VARCHAR(30) NOT NULL,
+  `created_at` DATETIME DEFAULT (CURRENT_TIMESTAMP),
+  `expires_at` DATETIME NOT NULL,
+  `used_at` DATETIME
+);
+
+CREATE INDEX `idx_user_action_tokens_user_id` ON `user_action_tokens` (`user_id`);
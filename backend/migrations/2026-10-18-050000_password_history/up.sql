@@ -0,0 +1,9 @@
+-- hashes of recently used passwords, checked so they aren't set again
+CREATE TABLE IF NOT EXISTS `password_history` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT NOT NULL,
+  `password_hash` VARCHAR(255) NOT NULL,
+  `created_at` DATETIME DEFAULT (CURRENT_TIMESTAMP)
+);
+
+CREATE INDEX `idx_password_history_user_id` ON `password_history` (`user_id`);
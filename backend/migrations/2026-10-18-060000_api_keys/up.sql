@@ -0,0 +1,18 @@
+-- service accounts only authenticate with API keys
+ALTER TABLE `users` ADD COLUMN `is_service_account` BOOLEAN NOT NULL DEFAULT false;
+
+-- personal access tokens, only the SHA-256 of the secret is stored
+CREATE TABLE IF NOT EXISTS `api_keys` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT NOT NULL,
+  `name` VARCHAR(100) NOT NULL,
+  `prefix` VARCHAR(20) NOT NULL,
+  `key_hash` VARCHAR(64) NOT NULL UNIQUE,
+  `scopes` TEXT NOT NULL,
+  `created_at` DATETIME DEFAULT (CURRENT_TIMESTAMP),
+  `last_used_at` DATETIME,
+  `expires_at` DATETIME,
+  `revoked` BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX `idx_api_keys_user_id` ON `api_keys` (`user_id`);
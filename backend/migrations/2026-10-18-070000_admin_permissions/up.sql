@@ -0,0 +1,19 @@
+-- routes are authorized through role_permissions now, give the admin role every action
+-- on the resources that used to be admin-only
+INSERT INTO `roles` (`name`, `description`)
+SELECT 'admin', 'Administrator'
+WHERE NOT EXISTS (SELECT 1 FROM `roles` WHERE `name` = 'admin');
+
+INSERT INTO `permissions` (`resource`, `action`, `description`)
+SELECT r.resource, (SELECT GROUP_CONCAT(`id` ORDER BY `id`) FROM `actions`), 'Seeded for the admin role'
+FROM (
+  SELECT 'user' AS resource
+  UNION ALL SELECT 'role'
+  UNION ALL SELECT 'service-account'
+) r;
+
+INSERT INTO `role_permissions` (`role_id`, `permission_id`)
+SELECT ro.id, p.id
+FROM `roles` ro
+JOIN `permissions` p ON p.description = 'Seeded for the admin role'
+WHERE ro.name = 'admin';
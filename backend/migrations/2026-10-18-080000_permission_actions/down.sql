@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `permissions` ADD COLUMN `action` VARCHAR(50) NOT NULL DEFAULT '';
+
+UPDATE `permissions` p
+SET p.action = COALESCE(
+  (SELECT GROUP_CONCAT(pa.action_id ORDER BY pa.action_id)
+   FROM `permission_actions` pa
+   WHERE pa.permission_id = p.id),
+  '');
+
+DROP TABLE IF EXISTS permission_actions;
@@ -0,0 +1,17 @@
+-- the actions a permission grants, replacing the comma-separated `permissions.action`
+CREATE TABLE IF NOT EXISTS `permission_actions` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `permission_id` INT NOT NULL,
+  `action_id` INT NOT NULL,
+  UNIQUE KEY `uq_permission_actions` (`permission_id`, `action_id`),
+  FOREIGN KEY (`permission_id`) REFERENCES `permissions` (`id`) ON DELETE CASCADE,
+  FOREIGN KEY (`action_id`) REFERENCES `actions` (`id`) ON DELETE CASCADE
+);
+
+-- ids that no longer exist in `actions` are dropped, the old loader ignored them as well
+INSERT INTO `permission_actions` (`permission_id`, `action_id`)
+SELECT p.id, a.id
+FROM `permissions` p
+JOIN `actions` a ON FIND_IN_SET(a.id, REPLACE(p.action, ' ', '')) > 0;
+
+ALTER TABLE `permissions` DROP COLUMN `action`;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DELETE rp FROM `role_permissions` rp
+JOIN `permissions` p ON p.id = rp.permission_id
+WHERE p.description = 'Seeded for permission management';
+DELETE FROM `permissions` WHERE `description` = 'Seeded for permission management';
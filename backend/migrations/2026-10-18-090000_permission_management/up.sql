@@ -0,0 +1,19 @@
+-- let the admin role manage permissions and the action vocabulary
+INSERT INTO `permissions` (`resource`, `description`)
+SELECT r.resource, 'Seeded for permission management'
+FROM (
+  SELECT 'permission' AS resource
+  UNION ALL SELECT 'action'
+) r;
+
+INSERT INTO `permission_actions` (`permission_id`, `action_id`)
+SELECT p.id, a.id
+FROM `permissions` p
+JOIN `actions` a ON a.`key` IN ('CREATE', 'UPDATE', 'DELETE', 'READ')
+WHERE p.description = 'Seeded for permission management';
+
+INSERT INTO `role_permissions` (`role_id`, `permission_id`)
+SELECT ro.id, p.id
+FROM `roles` ro
+JOIN `permissions` p ON p.description = 'Seeded for permission management'
+WHERE ro.name = 'admin';
@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX `idx_user_roles_role_id` ON `user_roles`;
+ALTER TABLE `user_roles`
+  DROP INDEX `uq_user_roles`,
+  DROP COLUMN `expires_at`,
+  DROP COLUMN `granted_at`;
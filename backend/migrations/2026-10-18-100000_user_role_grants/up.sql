@@ -0,0 +1,11 @@
+-- keep one row per user and role before the pair becomes unique
+DELETE ur FROM `user_roles` ur
+JOIN `user_roles` dup ON dup.user_id = ur.user_id AND dup.role_id = ur.role_id AND dup.id < ur.id;
+
+-- grants without `expires_at` are permanent
+ALTER TABLE `user_roles`
+  ADD COLUMN `granted_at` DATETIME DEFAULT (CURRENT_TIMESTAMP),
+  ADD COLUMN `expires_at` DATETIME,
+  ADD UNIQUE KEY `uq_user_roles` (`user_id`, `role_id`);
+
+CREATE INDEX `idx_user_roles_role_id` ON `user_roles` (`role_id`);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `roles` DROP FOREIGN KEY `fk_roles_parent_role_id`;
+ALTER TABLE `roles` DROP COLUMN `parent_role_id`;
@@ -0,0 +1,4 @@
+-- a role inherits every permission of its parent, e.g. editor -> viewer
+ALTER TABLE `roles`
+  ADD COLUMN `parent_role_id` INT,
+  ADD CONSTRAINT `fk_roles_parent_role_id` FOREIGN KEY (`parent_role_id`) REFERENCES `roles` (`id`);
@@ -0,0 +1,46 @@
+-- sidebar of the admin frontend, an item is shown when the user holds `resource`:`action`
+-- (or always when `resource` is NULL)
+CREATE TABLE IF NOT EXISTS `menu_items` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `parent_id` INT,
+  `code` VARCHAR(50) NOT NULL UNIQUE,
+  `label_en_us` VARCHAR(100) NOT NULL,
+  `label_zh_cn` VARCHAR(100) NOT NULL,
+  `icon` VARCHAR(50),
+  `path` VARCHAR(255) NOT NULL,
+  `resource` VARCHAR(50),
+  `action` VARCHAR(50),
+  `sort_order` INT NOT NULL DEFAULT 0,
+  CONSTRAINT `fk_menu_items_parent_id` FOREIGN KEY (`parent_id`) REFERENCES `menu_items` (`id`) ON DELETE CASCADE
+);
+
+-- the menu the frontend used to mock
+INSERT INTO `menu_items` (`code`, `label_en_us`, `label_zh_cn`, `icon`, `path`, `sort_order`) VALUES
+('dashboard', 'Dashboard', '首页', 'dashboard', '/dashboard', 10),
+('documentation', 'Documentation', '文档', 'documentation', '/documentation', 20),
+('guide', 'Guide', '引导', 'guide', '/guide', 30),
+('permission', 'Permission', '权限', 'permission', '/permission', 40),
+('component', 'Component', '组件', 'permission', '/component', 50),
+('business', 'Business', '业务', 'permission', '/business', 60);
+
+INSERT INTO `menu_items` (`parent_id`, `code`, `label_en_us`, `label_zh_cn`, `path`, `sort_order`)
+SELECT p.id, c.code, c.label_en_us, c.label_zh_cn, c.path, c.sort_order
+FROM (
+  SELECT 'permission' AS parent, 'routePermission' AS code, 'Route Permission' AS label_en_us, '路由权限' AS label_zh_cn, '/permission/route' AS path, 10 AS sort_order
+  UNION ALL SELECT 'permission', 'notFound', '404', '404', '/permission/404', 20
+  UNION ALL SELECT 'component', 'componentForm', 'Form', '表单', '/component/form', 10
+  UNION ALL SELECT 'component', 'componentTable', 'Table', '表格', '/component/table', 20
+  UNION ALL SELECT 'component', 'componentSearch', 'Search', '查询', '/component/search', 30
+  UNION ALL SELECT 'component', 'componentAside', 'Aside', '侧边栏', '/component/aside', 40
+  UNION ALL SELECT 'component', 'componentTabs', 'Tabs', '选项卡', '/component/tabs', 50
+  UNION ALL SELECT 'component', 'componentRadioCards', 'Radio Cards', '单选卡片', '/component/radio-cards', 60
+  UNION ALL SELECT 'business', 'basic', 'Basic', '基本', '/business/basic', 10
+  UNION ALL SELECT 'business', 'withSearch', 'WithSearch', '带查询', '/business/with-search', 20
+  UNION ALL SELECT 'business', 'withAside', 'WithAside', '带侧边栏', '/business/with-aside', 30
+  UNION ALL SELECT 'business', 'withRadioCard', 'With Nav Tabs', '带单选卡片', '/business/with-radio-cards', 40
+  UNION ALL SELECT 'business', 'withTabs', 'With Tabs', '带选项卡', '/business/with-tabs', 50
+) c
+JOIN `menu_items` p ON p.code = c.parent;
+
+-- only users who can manage roles see the permission pages
+UPDATE `menu_items` SET `resource` = 'role', `action` = 'READ' WHERE `code` = 'permission';
@@ -0,0 +1,2 @@
+-- bumped whenever the user's effective permissions may have changed, copied into access tokens
+ALTER TABLE `users` ADD COLUMN `permissions_version` INT NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS `audit_logs`;
+ALTER TABLE `users` DROP COLUMN `department`;
+ALTER TABLE `permissions` DROP COLUMN `policy`;
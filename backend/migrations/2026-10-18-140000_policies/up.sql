@@ -0,0 +1,24 @@
+-- optional rule a grant only applies under, JSON read by domain::permission::policy
+ALTER TABLE `permissions` ADD COLUMN `policy` TEXT NULL;
+
+-- exposed to policies as subject.department and resource.department
+ALTER TABLE `users` ADD COLUMN `department` VARCHAR(100) NULL;
+
+-- every authorization decision of a permission-guarded route, with its reason
+CREATE TABLE IF NOT EXISTS `audit_logs` (
+  `id` INT PRIMARY KEY AUTO_INCREMENT,
+  `user_id` INT NOT NULL,
+  `api_key_id` INT,
+  `resource` VARCHAR(50) NOT NULL,
+  `action` VARCHAR(50) NOT NULL,
+  `resource_id` VARCHAR(100),
+  `allowed` BOOLEAN NOT NULL,
+  `reason` VARCHAR(500) NOT NULL,
+  `method` VARCHAR(10) NOT NULL,
+  `path` VARCHAR(255) NOT NULL,
+  `ip_address` VARCHAR(45),
+  `created_at` DATETIME NOT NULL DEFAULT (CURRENT_TIMESTAMP)
+);
+
+CREATE INDEX `idx_audit_logs_user_id` ON `audit_logs` (`user_id`);
+CREATE INDEX `idx_audit_logs_created_at` ON `audit_logs` (`created_at`);
@@ -0,0 +1,3 @@
+-- bumped by every edit, served as the ETag the If-Match of a PATCH has to repeat
+ALTER TABLE `users` ADD COLUMN `version` INT NOT NULL DEFAULT 1;
+ALTER TABLE `users` ADD COLUMN `updated_at` DATETIME NULL;
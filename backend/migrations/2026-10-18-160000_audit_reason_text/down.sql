@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE `audit_logs` MODIFY `reason` VARCHAR(500) NOT NULL;
@@ -0,0 +1,2 @@
+-- policy reasons have no fixed length, don't cut them off
+ALTER TABLE `audit_logs` MODIFY `reason` TEXT NOT NULL;
@@ -11,22 +11,13 @@ pub struct ApiError(CommonError);
 
 impl ApiError {
     pub fn bad_request(cause: String) -> Self {
-        ApiError(CommonError {
-            message: cause,
-            code: 400,
-        })
+        ApiError(CommonError::new(cause, 400))
     }
     pub fn forbidden(cause: String) -> Self {
-        ApiError(CommonError {
-            message: cause,
-            code: 403,
-        })
+        ApiError(CommonError::new(cause, 403))
     }
     pub fn unauthorized(cause: String) -> Self {
-        ApiError(CommonError {
-            message: cause,
-            code: 401,
-        })
+        ApiError(CommonError::new(cause, 401))
     }
 }
 
@@ -45,7 +36,8 @@ impl std::fmt::Display for ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         (
-            axum::http::StatusCode::from_u16(self.0.code as u16).unwrap(),
+            axum::http::StatusCode::from_u16(self.0.code as u16)
+                .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
             Json(self.0),
         )
             .into_response()
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use serde::Deserialize;
+
+use crate::{app_axum::{error::ApiError, state::AppState}, domain::user::repo::UserIdentity};
+
+#[derive(Debug, Deserialize)]
+pub struct EmailRequest {
+    email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetPasswordRequest {
+    token: String,
+    new_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailRequest {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    current_password: String,
+    new_password: String,
+}
+
+pub struct AccountHandler;
+
+impl AccountHandler {
+    // 202 whether or not the email is registered
+    pub async fn forgot_password(
+        state: State<Arc<AppState>>,
+        Json(data): Json<EmailRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let account_service= state.account_service.clone();
+        account_service.request_password_reset(&data.email).await?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    pub async fn reset_password(
+        state: State<Arc<AppState>>,
+        Json(data): Json<ResetPasswordRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let account_service= state.account_service.clone();
+        account_service.reset_password(&data.token, &data.new_password).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn verify_email(
+        state: State<Arc<AppState>>,
+        Json(data): Json<VerifyEmailRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let account_service= state.account_service.clone();
+        account_service.verify_email(&data.token).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    // 202 whether or not the email is registered
+    pub async fn resend_verification(
+        state: State<Arc<AppState>>,
+        Json(data): Json<EmailRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let account_service= state.account_service.clone();
+        account_service.resend_verification(&data.email).await?;
+
+        Ok(StatusCode::ACCEPTED)
+    }
+
+    // other sessions are logged out, the current one stays
+    pub async fn change_password(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<ChangePasswordRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let account_service= state.account_service.clone();
+        account_service
+            .change_password(identity.user_id, &identity.session_id, &data.current_password, &data.new_password)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, http::StatusCode, Extension, Json};
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::Deserialize;
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::api_key_service::CreatedApiKey, domain::{security::api_key::ApiKey, user::repo::{User, UserIdentity}}};
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    name: String,
+    scopes: Vec<String>,
+    expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateServiceAccountRequest {
+    username: String,
+    email: Option<String>,
+}
+
+pub struct ApiKeyHandler;
+
+impl ApiKeyHandler {
+    pub async fn list_mine(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<ApiKey>>, ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        let keys = api_key_service.list(identity.user_id, page).await?;
+
+        Ok(Json(keys))
+    }
+
+    pub async fn create_mine(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<CreateApiKeyRequest>,
+    ) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+        // an API key can't mint keys for itself
+        if identity.api_key_id.is_some() {
+            return Err(ApiError::forbidden("API keys can't create API keys".to_string()));
+        }
+        let api_key_service= state.api_key_service.clone();
+        let key = api_key_service
+            .create(identity.user_id, data.name, data.scopes, data.expires_at)
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(key)))
+    }
+
+    pub async fn revoke_mine(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(key_id): Path<i32>,
+    ) -> Result<StatusCode, ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        api_key_service.revoke(identity.user_id, key_id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn create_service_account(
+        state: State<Arc<AppState>>,
+        Json(data): Json<CreateServiceAccountRequest>,
+    ) -> Result<(StatusCode, Json<User>), ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        let user = api_key_service.create_service_account(data.username, data.email).await?;
+
+        Ok((StatusCode::CREATED, Json(user)))
+    }
+
+    pub async fn list_of_service_account(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<ApiKey>>, ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        let account = api_key_service.get_service_account(user_id).await?;
+        let keys = api_key_service.list(account.id, page).await?;
+
+        Ok(Json(keys))
+    }
+
+    pub async fn create_for_service_account(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+        Json(data): Json<CreateApiKeyRequest>,
+    ) -> Result<(StatusCode, Json<CreatedApiKey>), ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        let account = api_key_service.get_service_account(user_id).await?;
+        let key = api_key_service
+            .create(account.id, data.name, data.scopes, data.expires_at)
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(key)))
+    }
+
+    pub async fn revoke_of_service_account(
+        state: State<Arc<AppState>>,
+        Path((user_id, key_id)): Path<(i32, i32)>,
+    ) -> Result<StatusCode, ApiError> {
+        let api_key_service= state.api_key_service.clone();
+        let account = api_key_service.get_service_account(user_id).await?;
+        api_key_service.revoke(account.id, key_id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
@@ -1,10 +1,28 @@
 
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 
-use axum::{extract::State, Json};
+use axum::{extract::{ConnectInfo, State}, http::{header::USER_AGENT, HeaderMap, StatusCode}, Extension, Json};
 use serde::{Deserialize, Serialize};
 
-use crate::{app_axum::{error::ApiError, state::AppState}, domain::user::repo::User};
+use crate::{app_axum::{error::ApiError, state::AppState}, application::{auth_service::LoginOutcome, mfa_service::MfaEnrollment}, domain::{security::token::{ClientInfo, TokenPair}, user::repo::{User, UserIdentity}}};
+
+pub const DEVICE_ID_HEADER: &str = "X-Device-Id";
+
+// device fields are truncated to the column sizes of `tokens`
+pub(crate) fn client_info(addr: SocketAddr, headers: &HeaderMap) -> ClientInfo {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    ClientInfo {
+        device_id: header(DEVICE_ID_HEADER).map(|v| v.chars().take(50).collect()),
+        device_info: header(USER_AGENT.as_str()),
+        ip_address: Some(addr.ip().to_string()),
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
@@ -19,10 +37,44 @@ pub struct CreateUserRequest {
     password: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaLoginRequest {
+    mfa_token: String,
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MfaEnrollRequest {
+    mfa_token: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
-    token: String,
+    #[serde(flatten)]
+    tokens: TokenPair,
     user: User,
+    // only set when this login finished a required 2FA enrollment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovery_codes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MfaRequiredResponse {
+    mfa_required: bool,
+    mfa_token: String,
+    enrollment_required: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginStepResponse {
+    Authenticated(Box<LoginResponse>),
+    MfaRequired(MfaRequiredResponse),
 }
 
 pub struct AuthHandler;
@@ -30,27 +82,88 @@ pub struct AuthHandler;
 impl AuthHandler {
     pub async fn login(
         state: State<Arc<AppState>>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
         Json(data): Json<LoginRequest>,
+    ) -> Result<Json<LoginStepResponse>, ApiError> {
+        let auth_service= state.auth_service.clone();
+        let outcome = auth_service
+            .login(&data.email_or_username, &data.password, client_info(addr, &headers))
+            .await?;
+
+        let rep = match outcome {
+            LoginOutcome::Authenticated { user, tokens } => {
+                LoginStepResponse::Authenticated(Box::new(LoginResponse { user, tokens, recovery_codes: None }))
+            }
+            LoginOutcome::MfaRequired { mfa_token, enrollment_required } => {
+                LoginStepResponse::MfaRequired(MfaRequiredResponse { mfa_required: true, mfa_token, enrollment_required })
+            }
+        };
+
+        Ok(Json(rep))
+    }
+
+    pub async fn login_mfa(
+        state: State<Arc<AppState>>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
+        Json(data): Json<MfaLoginRequest>,
     ) -> Result<Json<LoginResponse>, ApiError> {
         let auth_service= state.auth_service.clone();
-        let (user, token) = auth_service
-            .login(&data.email_or_username, &data.password)
+        let (user, tokens, recovery_codes) = auth_service
+            .login_mfa(&data.mfa_token, &data.code, client_info(addr, &headers))
             .await?;
 
-        let rep = LoginResponse { user, token };
+        let rep = LoginResponse { user, tokens, recovery_codes };
 
         Ok(Json(rep))
     }
 
+    pub async fn login_mfa_enroll(
+        state: State<Arc<AppState>>,
+        Json(data): Json<MfaEnrollRequest>,
+    ) -> Result<Json<MfaEnrollment>, ApiError> {
+        let auth_service= state.auth_service.clone();
+        let enrollment = auth_service.enroll_mfa(&data.mfa_token).await?;
+
+        Ok(Json(enrollment))
+    }
+
     pub async fn register( state: State<Arc<AppState>>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
         Json(data): Json<CreateUserRequest>,)-> Result<Json<LoginResponse>, ApiError> {
             let auth_service= state.auth_service.clone();
-            let (user, token) = auth_service
-                .register(data.username, data.email,data.password)
+            let (user, tokens) = auth_service
+                .register(data.username, data.email,data.password, client_info(addr, &headers))
                 .await?;
     
-            let rep = LoginResponse { user, token };
+            let rep = LoginResponse { user, tokens, recovery_codes: None };
     
             Ok(Json(rep))
     }
+
+    pub async fn refresh(
+        state: State<Arc<AppState>>,
+        ConnectInfo(addr): ConnectInfo<SocketAddr>,
+        headers: HeaderMap,
+        Json(data): Json<RefreshTokenRequest>,
+    ) -> Result<Json<TokenPair>, ApiError> {
+        let auth_service= state.auth_service.clone();
+        let tokens = auth_service
+            .refresh(&data.refresh_token, client_info(addr, &headers))
+            .await?;
+
+        Ok(Json(tokens))
+    }
+
+    pub async fn logout(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+    ) -> Result<StatusCode, ApiError> {
+        let auth_service= state.auth_service.clone();
+        auth_service.logout(&identity).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
 }
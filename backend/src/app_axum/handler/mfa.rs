@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, State}, http::StatusCode, Extension, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::mfa_service::MfaEnrollment, domain::{role::repo::Role, user::repo::UserIdentity}};
+
+#[derive(Debug, Deserialize)]
+pub struct MfaCodeRequest {
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleMfaRequest {
+    required: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
+}
+
+pub struct MfaHandler;
+
+impl MfaHandler {
+    pub async fn enroll(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+    ) -> Result<Json<MfaEnrollment>, ApiError> {
+        let mfa_service= state.mfa_service.clone();
+        let enrollment = mfa_service.enroll(identity.user_id, &identity.email).await?;
+
+        Ok(Json(enrollment))
+    }
+
+    pub async fn confirm(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<MfaCodeRequest>,
+    ) -> Result<Json<RecoveryCodesResponse>, ApiError> {
+        let mfa_service= state.mfa_service.clone();
+        let recovery_codes = mfa_service.confirm(identity.user_id, &data.code).await?;
+
+        Ok(Json(RecoveryCodesResponse { recovery_codes }))
+    }
+
+    pub async fn disable(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<MfaCodeRequest>,
+    ) -> Result<StatusCode, ApiError> {
+        let mfa_service= state.mfa_service.clone();
+        mfa_service.disable(identity.user_id, &data.code).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn require_for_role(
+        state: State<Arc<AppState>>,
+        Path(role_id): Path<i32>,
+        Json(data): Json<RoleMfaRequest>,
+    ) -> Result<Json<Role>, ApiError> {
+        let mfa_service= state.mfa_service.clone();
+        let role = mfa_service.set_role_requirement(role_id, data.required).await?;
+
+        Ok(Json(role))
+    }
+}
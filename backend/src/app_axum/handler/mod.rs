@@ -1,3 +1,11 @@
 pub mod auth;
 pub mod user;
-pub mod health;
\ No newline at end of file
+pub mod health;
+pub mod session;
+pub mod well_known;
+pub mod mfa;
+pub mod account;
+pub mod api_key;
+pub mod role;
+pub mod permission;
+pub mod profile;
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use common_model::page::{PageData, PageRequest};
+use serde::Deserialize;
+
+use crate::{app_axum::{error::ApiError, state::AppState}, domain::permission::{policy::Policy, repo::{Action, Permission}}};
+
+#[derive(Debug, Deserialize)]
+pub struct PermissionRequest {
+    resource: String,
+    // action keys, e.g. ["READ", "EXPORT"]
+    actions: Vec<String>,
+    description: Option<String>,
+    // e.g. {"eq": [{"attr": "subject.id"}, {"attr": "resource.id"}]}
+    #[serde(default)]
+    policy: Option<Policy>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateActionRequest {
+    key: String,
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateActionRequest {
+    description: String,
+}
+
+pub struct PermissionHandler;
+
+impl PermissionHandler {
+    pub async fn list(
+        state: State<Arc<AppState>>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<Permission>>, ApiError> {
+        let permission_service= state.permission_service.clone();
+        let permissions = permission_service.list(page).await?;
+
+        Ok(Json(permissions))
+    }
+
+    pub async fn get(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<Json<Permission>, ApiError> {
+        let permission_service= state.permission_service.clone();
+        let permission = permission_service.get(id).await?;
+
+        Ok(Json(permission))
+    }
+
+    pub async fn create(
+        state: State<Arc<AppState>>,
+        Json(data): Json<PermissionRequest>,
+    ) -> Result<(StatusCode, Json<Permission>), ApiError> {
+        let permission_service= state.permission_service.clone();
+        let permission = permission_service
+            .create(data.resource, data.actions, data.description, data.policy)
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(permission)))
+    }
+
+    pub async fn update(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+        Json(data): Json<PermissionRequest>,
+    ) -> Result<Json<Permission>, ApiError> {
+        let permission_service= state.permission_service.clone();
+        let permission = permission_service
+            .update(id, data.resource, data.actions, data.description, data.policy)
+            .await?;
+
+        Ok(Json(permission))
+    }
+
+    pub async fn delete(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<StatusCode, ApiError> {
+        let permission_service= state.permission_service.clone();
+        permission_service.delete(id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn list_actions(
+        state: State<Arc<AppState>>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<Action>>, ApiError> {
+        let permission_service= state.permission_service.clone();
+        let actions = permission_service.list_actions(page).await?;
+
+        Ok(Json(actions))
+    }
+
+    pub async fn create_action(
+        state: State<Arc<AppState>>,
+        Json(data): Json<CreateActionRequest>,
+    ) -> Result<(StatusCode, Json<Action>), ApiError> {
+        let permission_service= state.permission_service.clone();
+        let action = permission_service.create_action(data.key, data.description).await?;
+
+        Ok((StatusCode::CREATED, Json(action)))
+    }
+
+    pub async fn update_action(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+        Json(data): Json<UpdateActionRequest>,
+    ) -> Result<Json<Action>, ApiError> {
+        let permission_service= state.permission_service.clone();
+        let action = permission_service.update_action(id, data.description).await?;
+
+        Ok(Json(action))
+    }
+
+    pub async fn delete_action(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<StatusCode, ApiError> {
+        let permission_service= state.permission_service.clone();
+        permission_service.delete_action(id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
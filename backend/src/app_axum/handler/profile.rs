@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Extension, Json};
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::profile_service::{MenuNode, Profile}, domain::user::repo::UserIdentity};
+
+pub struct ProfileHandler;
+
+impl ProfileHandler {
+    pub async fn me(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+    ) -> Result<Json<Profile>, ApiError> {
+        let profile_service= state.profile_service.clone();
+        let profile = profile_service.get_profile(&identity).await?;
+
+        Ok(Json(profile))
+    }
+
+    pub async fn menu(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+    ) -> Result<Json<Vec<MenuNode>>, ApiError> {
+        let profile_service= state.profile_service.clone();
+        let menu = profile_service.get_menu(&identity).await?;
+
+        Ok(Json(menu))
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::Deserialize;
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::role_service::EffectivePermission, domain::{permission::repo::Permission, role::repo::{Role, RoleGrant, RoleMember}}};
+
+#[derive(Debug, Deserialize)]
+pub struct RoleRequest {
+    name: String,
+    description: Option<String>,
+    // role to inherit permissions from
+    #[serde(default)]
+    parent_role_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrantRoleRequest {
+    // leave out for a permanent grant
+    #[serde(default)]
+    expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GrantMembersRequest {
+    user_ids: Vec<i32>,
+    #[serde(default)]
+    expires_at: Option<NaiveDateTime>,
+}
+
+pub struct RoleHandler;
+
+impl RoleHandler {
+    pub async fn list(
+        state: State<Arc<AppState>>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<Role>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let roles = role_service.list(page).await?;
+
+        Ok(Json(roles))
+    }
+
+    pub async fn get(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<Json<Role>, ApiError> {
+        let role_service= state.role_service.clone();
+        let role = role_service.get(id).await?;
+
+        Ok(Json(role))
+    }
+
+    pub async fn create(
+        state: State<Arc<AppState>>,
+        Json(data): Json<RoleRequest>,
+    ) -> Result<(StatusCode, Json<Role>), ApiError> {
+        let role_service= state.role_service.clone();
+        let role = role_service.create(data.name, data.description, data.parent_role_id).await?;
+
+        Ok((StatusCode::CREATED, Json(role)))
+    }
+
+    pub async fn update(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+        Json(data): Json<RoleRequest>,
+    ) -> Result<Json<Role>, ApiError> {
+        let role_service= state.role_service.clone();
+        let role = role_service.update(id, data.name, data.description, data.parent_role_id).await?;
+
+        Ok(Json(role))
+    }
+
+    pub async fn delete(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<StatusCode, ApiError> {
+        let role_service= state.role_service.clone();
+        role_service.delete(id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn list_permissions(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<Json<Vec<Permission>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let permissions = role_service.get_permissions(id).await?;
+
+        Ok(Json(permissions))
+    }
+
+    pub async fn list_effective_permissions(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+    ) -> Result<Json<Vec<EffectivePermission>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let permissions = role_service.get_effective_permissions(id).await?;
+
+        Ok(Json(permissions))
+    }
+
+    pub async fn add_permission(
+        state: State<Arc<AppState>>,
+        Path((id, permission_id)): Path<(i32, i32)>,
+    ) -> Result<Json<Vec<Permission>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let permissions = role_service.add_permission(id, permission_id).await?;
+
+        Ok(Json(permissions))
+    }
+
+    pub async fn remove_permission(
+        state: State<Arc<AppState>>,
+        Path((id, permission_id)): Path<(i32, i32)>,
+    ) -> Result<StatusCode, ApiError> {
+        let role_service= state.role_service.clone();
+        role_service.remove_permission(id, permission_id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn list_members(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<RoleMember>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let members = role_service.get_members(id, page).await?;
+
+        Ok(Json(members))
+    }
+
+    pub async fn add_members(
+        state: State<Arc<AppState>>,
+        Path(id): Path<i32>,
+        Json(data): Json<GrantMembersRequest>,
+    ) -> Result<Json<PageData<RoleMember>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let members = role_service.grant_to_users(data.user_ids, id, data.expires_at).await?;
+
+        Ok(Json(members))
+    }
+
+    pub async fn list_of_user(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<RoleGrant>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let grants = role_service.get_user_roles(user_id, page).await?;
+
+        Ok(Json(grants))
+    }
+
+    pub async fn grant_to_user(
+        state: State<Arc<AppState>>,
+        Path((user_id, id)): Path<(i32, i32)>,
+        Json(data): Json<GrantRoleRequest>,
+    ) -> Result<Json<PageData<RoleGrant>>, ApiError> {
+        let role_service= state.role_service.clone();
+        let grants = role_service.grant(user_id, id, data.expires_at).await?;
+
+        Ok(Json(grants))
+    }
+
+    pub async fn revoke_from_user(
+        state: State<Arc<AppState>>,
+        Path((user_id, id)): Path<(i32, i32)>,
+    ) -> Result<StatusCode, ApiError> {
+        let role_service= state.role_service.clone();
+        role_service.revoke(user_id, id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
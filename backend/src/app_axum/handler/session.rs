@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, http::StatusCode, Extension, Json};
+use common_model::page::{PageData, PageRequest};
+use serde::Serialize;
+
+use crate::{app_axum::{error::ApiError, state::AppState}, domain::{security::token::Session, user::repo::UserIdentity}};
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionsResponse {
+    revoked: usize,
+}
+
+pub struct SessionHandler;
+
+impl SessionHandler {
+    pub async fn list_mine(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<Session>>, ApiError> {
+        let session_service= state.session_service.clone();
+        let sessions = session_service
+            .list(identity.user_id, Some(&identity.session_id), page)
+            .await?;
+
+        Ok(Json(sessions))
+    }
+
+    pub async fn revoke_mine(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(session_id): Path<String>,
+    ) -> Result<StatusCode, ApiError> {
+        let session_service= state.session_service.clone();
+        session_service.revoke(identity.user_id, session_id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    // "log out everywhere else"
+    pub async fn revoke_my_others(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+    ) -> Result<Json<RevokeSessionsResponse>, ApiError> {
+        let session_service= state.session_service.clone();
+        let revoked = session_service
+            .revoke_others(identity.user_id, identity.session_id)
+            .await?;
+
+        Ok(Json(RevokeSessionsResponse { revoked }))
+    }
+
+    pub async fn list_of_user(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(user_id): Path<i32>,
+        Query(page): Query<PageRequest>,
+    ) -> Result<Json<PageData<Session>>, ApiError> {
+        let session_service= state.session_service.clone();
+        let current = (identity.user_id == user_id).then_some(identity.session_id.as_str());
+        let sessions = session_service.list(user_id, current, page).await?;
+
+        Ok(Json(sessions))
+    }
+
+    pub async fn revoke_of_user(
+        state: State<Arc<AppState>>,
+        Path((user_id, session_id)): Path<(i32, String)>,
+    ) -> Result<StatusCode, ApiError> {
+        let session_service= state.session_service.clone();
+        session_service.revoke(user_id, session_id).await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn revoke_all_of_user(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+    ) -> Result<Json<RevokeSessionsResponse>, ApiError> {
+        let session_service= state.session_service.clone();
+        let revoked = session_service.revoke_all(user_id).await?;
+
+        Ok(Json(RevokeSessionsResponse { revoked }))
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::Arc;
+
+use axum::{body::Body, extract::{Multipart, Path, Query, State}, http::{header, HeaderMap, HeaderName, StatusCode}, Extension, Json};
+use common_model::{page::PageData, user::FilterUserRequest};
+use futures_util::StreamExt;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::user_service::ImportReport, domain::{error::CommonError, sheet::SheetFormat, user::repo::{User, UserIdentity, UserPatch}}};
+
+#[derive(Debug, Deserialize)]
+pub struct NewUserRequest {
+    username: String,
+    email: String,
+    password: String,
+    #[serde(default)]
+    department: Option<String>,
+    // role granted right away, needs role:UPDATE
+    #[serde(default)]
+    role_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    username: String,
+    email: String,
+    #[serde(default)]
+    department: Option<String>,
+}
+
+// only the fields sent are changed
+#[derive(Debug, Deserialize)]
+pub struct PatchUserRequest {
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    email: Option<String>,
+    // null clears it
+    #[serde(default, deserialize_with = "present")]
+    employee_id: Option<Option<i32>>,
+    #[serde(default)]
+    is_active: Option<bool>,
+    // null clears it
+    #[serde(default, deserialize_with = "present")]
+    department: Option<Option<String>>,
+}
+
+// tells `"field": null`, Some(None), apart from a field that isn't there, None
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// the ETag of a user is its version
+fn etag(user: &User) -> [(HeaderName, String); 1] {
+    [(header::ETAG, format!("\"{}\"", user.version))]
+}
+
+// the version If-Match names, None for "*". Err when the header is missing
+fn if_match(headers: &HeaderMap) -> Result<Option<i32>, ApiError> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Err(CommonError::new("If-Match with the ETag of the user is required", 428).into());
+    };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    // an ETag that isn't one of ours can't match any version
+    value.trim_start_matches("W/").trim_matches('"').parse::<i32>()
+        .map(Some)
+        .map_err(|_| CommonError::new("User was changed by someone else, reload it and try again", 412).into())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportParams {
+    // only check the rows, nothing is created
+    #[serde(default)]
+    dry_run: bool,
+    // "csv" or "xlsx", taken from the file name when missing
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    // "csv" (default) or "xlsx"
+    #[serde(default)]
+    format: Option<String>,
+}
+
+fn sheet_format(value: &str) -> Result<SheetFormat, ApiError> {
+    SheetFormat::parse(value).ok_or_else(|| ApiError::bad_request("Format must be csv or xlsx".to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeactivateUsersRequest {
+    user_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeactivateUsersResponse {
+    user_ids: Vec<i32>,
+}
+
+pub struct UserHandler;
+
+impl UserHandler {
+    pub async fn list(
+        state: State<Arc<AppState>>,
+        Query(filter): Query<FilterUserRequest>,
+    ) -> Result<Json<PageData<User>>, ApiError> {
+        let user_service= state.user_service.clone();
+        let users = user_service.list(filter).await?;
+
+        Ok(Json(users))
+    }
+
+    pub async fn get(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+    ) -> Result<([(HeaderName, String); 1], Json<User>), ApiError> {
+        let user_service= state.user_service.clone();
+        let user = user_service.get(user_id).await?;
+
+        Ok((etag(&user), Json(user)))
+    }
+
+    pub async fn create(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<NewUserRequest>,
+    ) -> Result<(StatusCode, Json<User>), ApiError> {
+        let user_service= state.user_service.clone();
+        let user = user_service
+            .create(&identity, data.username, data.email, data.password, data.department, data.role_id)
+            .await?;
+
+        Ok((StatusCode::CREATED, Json(user)))
+    }
+
+    // replaces username, email and department, If-Match as for patch
+    pub async fn update(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(user_id): Path<i32>,
+        headers: HeaderMap,
+        Json(data): Json<UpdateUserRequest>,
+    ) -> Result<([(HeaderName, String); 1], Json<User>), ApiError> {
+        let user_service= state.user_service.clone();
+        let version = if_match(&headers)?;
+        let patch = UserPatch {
+            username: Some(data.username),
+            email: Some(data.email),
+            department: Some(data.department),
+            ..Default::default()
+        };
+        let user = user_service.update(&identity, user_id, patch, version).await?;
+
+        Ok((etag(&user), Json(user)))
+    }
+
+    // needs the ETag the change was made on in If-Match, so it can't undo an edit it never saw
+    pub async fn patch(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(user_id): Path<i32>,
+        headers: HeaderMap,
+        Json(data): Json<PatchUserRequest>,
+    ) -> Result<([(HeaderName, String); 1], Json<User>), ApiError> {
+        let user_service= state.user_service.clone();
+        let version = if_match(&headers)?;
+        let patch = UserPatch {
+            username: data.username,
+            email: data.email,
+            email_verified: None,
+            employee_id: data.employee_id,
+            is_active: data.is_active,
+            department: data.department,
+        };
+        let user = user_service.update(&identity, user_id, patch, version).await?;
+
+        Ok((etag(&user), Json(user)))
+    }
+
+    pub async fn deactivate(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Path(user_id): Path<i32>,
+    ) -> Result<Json<User>, ApiError> {
+        let user_service= state.user_service.clone();
+        let user = user_service.deactivate(&identity, user_id).await?;
+
+        Ok(Json(user))
+    }
+
+    pub async fn deactivate_many(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Json(data): Json<DeactivateUsersRequest>,
+    ) -> Result<Json<DeactivateUsersResponse>, ApiError> {
+        let user_service= state.user_service.clone();
+        let user_ids = user_service.deactivate_many(&identity, data.user_ids).await?;
+
+        Ok(Json(DeactivateUsersResponse { user_ids }))
+    }
+
+    pub async fn reactivate(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+    ) -> Result<Json<User>, ApiError> {
+        let user_service= state.user_service.clone();
+        let user = user_service.reactivate(user_id).await?;
+
+        Ok(Json(user))
+    }
+
+    // multipart upload with the file in a `file` field
+    pub async fn import(
+        state: State<Arc<AppState>>,
+        Extension(identity): Extension<UserIdentity>,
+        Query(params): Query<ImportParams>,
+        mut multipart: Multipart,
+    ) -> Result<(StatusCode, Json<ImportReport>), ApiError> {
+        let user_service= state.user_service.clone();
+        let mut upload = None;
+        while let Some(field) = multipart.next_field().await.map_err(|e| ApiError::bad_request(e.body_text()))? {
+            if field.name() != Some("file") {
+                continue;
+            }
+            let format = match params.format.as_deref().or(field.file_name()) {
+                Some(format) => sheet_format(format)?,
+                None => return Err(ApiError::bad_request("Format must be csv or xlsx".to_string())),
+            };
+            let file = field.bytes().await.map_err(|e| ApiError::bad_request(e.body_text()))?;
+            upload = Some((format, file.to_vec()));
+        }
+        let (format, file) = upload.ok_or_else(|| ApiError::bad_request("The upload has no file field".to_string()))?;
+        let report = user_service.import(&identity, format, file, params.dry_run).await?;
+
+        let status = if report.dry_run {
+            StatusCode::OK
+        } else if report.errors.is_empty() {
+            StatusCode::CREATED
+        } else {
+            StatusCode::UNPROCESSABLE_ENTITY
+        };
+        Ok((status, Json(report)))
+    }
+
+    // takes the filters of the list, the file holds every page
+    pub async fn export(
+        state: State<Arc<AppState>>,
+        Query(filter): Query<FilterUserRequest>,
+        Query(params): Query<ExportParams>,
+    ) -> Result<([(HeaderName, String); 2], Body), ApiError> {
+        let user_service= state.user_service.clone();
+        let format = params.format.as_deref().map(sheet_format).transpose()?.unwrap_or(SheetFormat::Csv);
+        let chunks = user_service.export(filter, format).await?;
+
+        let headers = [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"users.{}\"", format.extension())),
+        ];
+        let body = Body::from_stream(chunks.map(|chunk| chunk.map_err(|e| std::io::Error::other(e.message))));
+        Ok((headers, body))
+    }
+
+    pub async fn unlock(
+        state: State<Arc<AppState>>,
+        Path(user_id): Path<i32>,
+    ) -> Result<Json<User>, ApiError> {
+        let user_service= state.user_service.clone();
+        let user = user_service.unlock(user_id).await?;
+
+        Ok(Json(user))
+    }
+}
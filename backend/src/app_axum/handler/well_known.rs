@@ -0,0 +1,12 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::app_axum::state::AppState;
+
+// lets other services verify our tokens without holding a signing key
+pub async fn jwks(state: State<Arc<AppState>>) -> Json<JwkSet> {
+    let security_service = state.security_service.clone();
+    Json(security_service.jwks().await)
+}
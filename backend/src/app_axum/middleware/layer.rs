@@ -1,9 +1,9 @@
 use async_trait::async_trait;
 use std::sync::Arc;
-use axum::{body::Body, extract::{Request, State}, http::StatusCode, response::Response};
+use axum::{body::Body, extract::{Request, State}, http::StatusCode, response::{IntoResponse, Response}};
 
 use super::{THandler, AUTHORIZATION_HEADER, BEARER};
-use crate::{app_axum::{error::ApiError, state::AppState}, domain::user::repo::UserIdentity};
+use crate::{app_axum::{error::ApiError, state::AppState}, domain::security::api_key::API_KEY_PREFIX};
 
 // layer check token
 #[derive(Debug, Clone)]
@@ -15,7 +15,6 @@ impl THandler for TokenLayer {
     where 
         B:Send
     {
-        let security_service = state.security_service.clone();
         let token = req
         .headers()
         .get(AUTHORIZATION_HEADER)
@@ -26,59 +25,19 @@ impl THandler for TokenLayer {
             .body(Body::from("Unauthorized"))
             .unwrap())?;
 
-        let result: Result<Request<B>, Response> = match security_service.verify_jwt(token.to_string()).await.map_err(|e| ApiError::from(e)){
-            Ok(value) => {
-               if value{
-                let claim= security_service.decode(token).await.unwrap();
-                let identity = UserIdentity {
-                    email: claim.claims.email.to_string(),
-                    user_id: claim.claims.sub as i32,
-                };
-                req.extensions_mut().insert(identity); 
-                
+        // API keys are opaque, everything else must be a JWT. Both are checked for expiry and revocation
+        let identity = if token.starts_with(API_KEY_PREFIX) {
+            state.api_key_service.authenticate(token).await
+        } else {
+            state.auth_service.authenticate(token).await
+        };
+        match identity.map_err(ApiError::from){
+            Ok(identity) => {
+                req.extensions_mut().insert(identity);
                 Ok(req)
-               } else{
-                Err(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from("Token verification failed"))
-                .unwrap())
-               }
             },
-            Err(e) => Err(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from("Token verification failed"))
-                .unwrap()),
-        }; 
-        result
+            Err(e) => Err(e.into_response()),
+        }
     }
 }
 
-
-// layer check role
-#[derive(Debug, Clone)]
-pub struct AuthorizationLayer;
-
-#[async_trait]
-impl THandler for AuthorizationLayer {
-    async fn handle_request<B>(req: Request<B>, State(state): State<Arc<AppState>>) -> Result<Request<B>, Response> 
-    where 
-        B:Send
-    {
-        let role_header = req.headers().get("Role");
-
-        let response = Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Body::from("Forbidden: You do not have the required permissions"))
-            .unwrap();
-
-        if let Some(role) = role_header {
-            if let Ok(role_str) = role.to_str() {
-                let role_owned = role_str.to_owned(); 
-                if role_owned == "admin" {
-                    return Ok(req);
-                }
-            }
-        }
-        Err(response)
-    }
-}
\ No newline at end of file
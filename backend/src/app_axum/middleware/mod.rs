@@ -1,4 +1,5 @@
 pub(crate) mod layer;
+pub(crate) mod require;
 
 use crate::app_axum::state::AppState;
 use async_trait::async_trait;
@@ -0,0 +1,130 @@
+use std::{net::SocketAddr, sync::Arc, task::{Context, Poll}};
+
+use axum::{body::Body, extract::{ConnectInfo, FromRequestParts, RawPathParams, Request, State}, http::StatusCode, middleware::Next, response::{IntoResponse, Response}};
+use futures_util::future::BoxFuture;
+use serde_json::{Map, Value};
+use tower::{Layer, Service};
+
+use crate::{app_axum::{error::ApiError, state::AppState}, application::authorization_service::AccessRequest, domain::{permission::repo::Type, user::repo::UserIdentity}};
+
+// permission a route needs, checked against the roles (and API key scopes) of the caller
+#[derive(Debug, Clone)]
+pub struct Requirement {
+    pub resource: &'static str,
+    pub action: Type,
+    // path parameter holding the id of the resource checked, "id" unless the route nests it
+    // below another resource, e.g. `role_id` in /users/{id}/roles/{role_id}
+    pub target: &'static str,
+}
+
+// layer check one declared permission, must run after TokenLayer
+#[derive(Clone)]
+pub struct RequireLayer {
+    state: State<Arc<AppState>>,
+    requirement: Requirement,
+}
+
+impl RequireLayer {
+    pub fn new(state: State<Arc<AppState>>, requirement: Requirement) -> Self {
+        Self { state, requirement }
+    }
+}
+
+impl<S> Layer<S> for RequireLayer {
+    type Service = RequireMiddleware<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireMiddleware {
+            inner,
+            state: self.state.clone(),
+            requirement: self.requirement.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequireMiddleware<S> {
+    inner: S,
+    state: State<Arc<AppState>>,
+    requirement: Requirement,
+}
+
+fn forbidden(reason: &str) -> Response {
+    Response::builder()
+        .status(StatusCode::FORBIDDEN)
+        .body(Body::from(format!("Forbidden: {}", reason)))
+        .unwrap()
+}
+
+// for routes that manage the account itself (sessions, API keys, MFA, password), which no key
+// scope covers. Must run after TokenLayer
+pub async fn interactive_only(req: Request, next: Next) -> Response {
+    match req.extensions().get::<UserIdentity>() {
+        Some(identity) if identity.api_key_id.is_none() => next.run(req).await,
+        Some(_) => forbidden("API keys can't manage the account, sign in instead"),
+        None => forbidden("You do not have the required permissions"),
+    }
+}
+
+// numeric params become numbers so policies can compare them with ids, e.g. resource.id == subject.id
+fn path_params(params: &RawPathParams) -> Map<String, Value> {
+    params
+        .iter()
+        .map(|(key, value)| {
+            let value = match value.parse::<i64>() {
+                Ok(number) => Value::from(number),
+                Err(_) => Value::from(value),
+            };
+            (key.to_string(), value)
+        })
+        .collect()
+}
+
+impl<S> Service<Request> for RequireMiddleware<S>
+where
+    S: Service<Request, Response = Response> + Send + Clone + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let State(state) = self.state.clone();
+        let requirement = self.requirement.clone();
+        let mut inner = self.inner.clone();
+
+        Box::pin(async move {
+            let Some(identity) = req.extensions().get::<UserIdentity>().cloned() else {
+                return Ok(forbidden("You do not have the required permissions"));
+            };
+
+            let (mut parts, body) = req.into_parts();
+            let target = match RawPathParams::from_request_parts(&mut parts, &()).await {
+                Ok(params) => path_params(&params),
+                Err(_) => Map::new(),
+            };
+            let request = AccessRequest {
+                resource: requirement.resource.to_string(),
+                action: requirement.action.clone(),
+                target_id: target.get(requirement.target).cloned(),
+                target,
+                method: parts.method.to_string(),
+                path: parts.uri.path().to_string(),
+                ip_address: parts.extensions.get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string()),
+            };
+            let req = Request::from_parts(parts, body);
+
+            let authorization_service = state.authorization_service.clone();
+            match authorization_service.authorize(&identity, request).await {
+                Ok(decision) if decision.allowed => inner.call(req).await,
+                Ok(decision) => Ok(forbidden(&decision.reason)),
+                Err(e) => Ok(ApiError::from(e).into_response()),
+            }
+        })
+    }
+}
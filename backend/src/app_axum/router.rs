@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use axum::{extract::State, middleware::from_fn, routing::MethodRouter, Router};
+
+use super::{middleware::{layer::TokenLayer, require::{interactive_only, RequireLayer, Requirement}, TLayer}, state::AppState};
+use crate::domain::permission::repo::Type;
+
+// everything below this prefix needs a token unless the route says otherwise
+pub const PROTECTED_PREFIX: &str = "/api/v1/";
+
+// what a caller needs to reach a route
+#[derive(Debug, Clone)]
+pub enum Access {
+    // nothing was declared, treated like Authenticated below the protected prefix
+    Undeclared,
+    Public,
+    Authenticated,
+    // a signed-in user, API keys are refused whatever their scopes
+    Interactive,
+    Permission(Requirement),
+}
+
+pub struct ApiRoute {
+    method_router: MethodRouter<Arc<AppState>>,
+    access: Access,
+}
+
+impl From<MethodRouter<Arc<AppState>>> for ApiRoute {
+    fn from(method_router: MethodRouter<Arc<AppState>>) -> Self {
+        Self { method_router, access: Access::Undeclared }
+    }
+}
+
+// declare the access of a route next to its handler, e.g. `post(UserHandler::unlock).require("user", Type::UPDATE)`.
+// route_layer wraps every method already on a MethodRouter, so declare one method per `.route` call
+pub trait RouteAccess {
+    fn require(self, resource: &'static str, action: Type) -> ApiRoute;
+    // like `require`, for routes where `param` and not `id` names the resource checked
+    fn require_on(self, resource: &'static str, action: Type, param: &'static str) -> ApiRoute;
+    fn authenticated(self) -> ApiRoute;
+    fn interactive(self) -> ApiRoute;
+    fn public(self) -> ApiRoute;
+}
+
+impl RouteAccess for MethodRouter<Arc<AppState>> {
+    fn require(self, resource: &'static str, action: Type) -> ApiRoute {
+        self.require_on(resource, action, "id")
+    }
+    fn require_on(self, resource: &'static str, action: Type, param: &'static str) -> ApiRoute {
+        ApiRoute { method_router: self, access: Access::Permission(Requirement { resource, action, target: param }) }
+    }
+    fn authenticated(self) -> ApiRoute {
+        ApiRoute { method_router: self, access: Access::Authenticated }
+    }
+    fn interactive(self) -> ApiRoute {
+        ApiRoute { method_router: self, access: Access::Interactive }
+    }
+    fn public(self) -> ApiRoute {
+        ApiRoute { method_router: self, access: Access::Public }
+    }
+}
+
+// router that puts the token and permission layers on each route from its declared access
+pub struct ApiRouter {
+    state: State<Arc<AppState>>,
+    router: Router<Arc<AppState>>,
+    undeclared: Vec<String>,
+}
+
+impl ApiRouter {
+    pub fn new(state: State<Arc<AppState>>) -> Self {
+        Self { state, router: Router::new(), undeclared: Vec::new() }
+    }
+
+    pub fn route(mut self, path: &str, route: impl Into<ApiRoute>) -> Self {
+        let ApiRoute { method_router, access } = route.into();
+        let protected = path.starts_with(PROTECTED_PREFIX);
+        let token_layer = TLayer::<TokenLayer>::new(self.state.clone());
+
+        // the last route_layer runs first, so the token is checked before the permission
+        let method_router = match access {
+            Access::Public => method_router,
+            Access::Undeclared if !protected => method_router,
+            Access::Undeclared => {
+                self.undeclared.push(path.to_string());
+                method_router.route_layer(token_layer)
+            }
+            Access::Authenticated => method_router.route_layer(token_layer),
+            Access::Interactive => method_router
+                .route_layer(from_fn(interactive_only))
+                .route_layer(token_layer),
+            Access::Permission(requirement) => method_router
+                .route_layer(RequireLayer::new(self.state.clone(), requirement))
+                .route_layer(token_layer),
+        };
+        self.router = self.router.route(path, method_router);
+        self
+    }
+
+    // flags routes below the protected prefix that do not say who may call them
+    pub fn build(self) -> Router {
+        for path in &self.undeclared {
+            tracing::warn!("Route {} declares no access requirement, it only checks the token", path);
+        }
+        self.router.with_state(self.state.0)
+    }
+}
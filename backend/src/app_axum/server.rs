@@ -1,18 +1,14 @@
 use std::{net::SocketAddr, sync::Arc};
 
-use axum::{extract::State, routing::{get, post}, Router};
-use tower::ServiceBuilder;
+use axum::{extract::State, routing::{delete, get, patch, post, put}, Router};
 use tower_http::cors::CorsLayer;
 
-use super::{handler::{auth::AuthHandler, health::health_check}, middleware::{layer::{AuthorizationLayer, TokenLayer}, TLayer}, state::AppState};
+use super::{handler::{account::AccountHandler, api_key::ApiKeyHandler, auth::AuthHandler, health::health_check, mfa::MfaHandler, permission::PermissionHandler, profile::ProfileHandler, role::RoleHandler, session::SessionHandler, user::UserHandler, well_known}, router::{ApiRouter, RouteAccess}, state::AppState};
+use crate::domain::permission::repo::Type;
 
-type _TokenLayer = TLayer<TokenLayer>;
-type _AuthorizationLayer = TLayer<AuthorizationLayer>;
 fn app_routes(state: State<Arc<AppState>>)->Router{
 
     // init layer
-    let token_layer= _TokenLayer::new(state.clone());
-    let authorization_layer= _AuthorizationLayer::new(state.clone());
     let cors_layer= CorsLayer::new()
                                         .allow_methods([axum::http::Method::GET,axum::http::Method::POST])
                                         .allow_headers([
@@ -21,28 +17,99 @@ fn app_routes(state: State<Arc<AppState>>)->Router{
                                         ])
                                         .allow_credentials(true);
 
-    // verifi token
-    let level_token=ServiceBuilder::new()
-                        .layer(token_layer);
+    // every route below /api/v1 declares who may call it, see ApiRouter
+    ApiRouter::new(state)
+    .route("/health_check",get(health_check) )
+    .route("/.well-known/jwks.json",get(well_known::jwks))
 
-    // các route của các module
-    // let module_routes = Router::new()
-    //                     .nest("/users", user_router(state.clone()));            
+    // public
+    .route("/api/v1/login",post(AuthHandler::login).public())
+    .route("/api/v1/login/mfa",post(AuthHandler::login_mfa).public())
+    .route("/api/v1/login/mfa/enroll",post(AuthHandler::login_mfa_enroll).public())
+    .route("/api/v1/register",post(AuthHandler::register).public())
+    .route("/api/v1/token/refresh",post(AuthHandler::refresh).public())
+    .route("/api/v1/password/forgot",post(AccountHandler::forgot_password).public())
+    .route("/api/v1/password/reset",post(AccountHandler::reset_password).public())
+    .route("/api/v1/email/verify",post(AccountHandler::verify_email).public())
+    .route("/api/v1/email/verify/resend",post(AccountHandler::resend_verification).public())
 
+    // the caller's own account
+    .route("/api/v1/logout",post(AuthHandler::logout).authenticated())
+    .route("/api/v1/me",get(ProfileHandler::me).authenticated())
+    .route("/api/v1/me/menu",get(ProfileHandler::menu).authenticated())
+    .route("/api/v1/me/sessions",get(SessionHandler::list_mine).interactive())
+    .route("/api/v1/me/sessions/logout-others",post(SessionHandler::revoke_my_others).interactive())
+    .route("/api/v1/me/sessions/{id}",delete(SessionHandler::revoke_mine).interactive())
+    .route("/api/v1/me/password",post(AccountHandler::change_password).interactive())
+    .route("/api/v1/me/tokens",get(ApiKeyHandler::list_mine).interactive())
+    .route("/api/v1/me/tokens",post(ApiKeyHandler::create_mine).interactive())
+    .route("/api/v1/me/tokens/{id}",delete(ApiKeyHandler::revoke_mine).interactive())
+    .route("/api/v1/me/mfa",delete(MfaHandler::disable).interactive())
+    .route("/api/v1/me/mfa/enroll",post(MfaHandler::enroll).interactive())
+    .route("/api/v1/me/mfa/confirm",post(MfaHandler::confirm).interactive())
 
-    Router::new()
-    .route("/health_check",get(health_check) )
-    .route("/api/v1/login",post(AuthHandler::login))
-    .route("/api/v1/register",post(AuthHandler::register))
-    .with_state(state.0)
-    
-    //.nest("/api/v1/", module_routes)
-    //.layer(level_2)
+    // administration
+    .route("/api/v1/users",get(UserHandler::list).require("user", Type::READ))
+    .route("/api/v1/users",post(UserHandler::create).require("user", Type::CREATE))
+    .route("/api/v1/users/deactivate",post(UserHandler::deactivate_many).require("user", Type::DELETE))
+    .route("/api/v1/users/import",post(UserHandler::import).require("user", Type::CREATE))
+    .route("/api/v1/users/export",get(UserHandler::export).require("user", Type::READ))
+    .route("/api/v1/users/{id}",get(UserHandler::get).require("user", Type::READ))
+    .route("/api/v1/users/{id}",put(UserHandler::update).require("user", Type::UPDATE))
+    .route("/api/v1/users/{id}",patch(UserHandler::patch).require("user", Type::UPDATE))
+    .route("/api/v1/users/{id}/deactivate",post(UserHandler::deactivate).require("user", Type::DELETE))
+    .route("/api/v1/users/{id}/reactivate",post(UserHandler::reactivate).require("user", Type::UPDATE))
+    .route("/api/v1/users/{id}/sessions",get(SessionHandler::list_of_user).require("user", Type::READ))
+    .route("/api/v1/users/{id}/sessions",delete(SessionHandler::revoke_all_of_user).require("user", Type::UPDATE))
+    .route("/api/v1/users/{id}/sessions/{session_id}",delete(SessionHandler::revoke_of_user).require("user", Type::UPDATE))
+    .route("/api/v1/users/{id}/roles",get(RoleHandler::list_of_user).require("user", Type::READ))
+    .route("/api/v1/users/{id}/roles/{role_id}",put(RoleHandler::grant_to_user).require_on("role", Type::UPDATE, "role_id"))
+    .route("/api/v1/users/{id}/roles/{role_id}",delete(RoleHandler::revoke_from_user).require_on("role", Type::UPDATE, "role_id"))
+    .route("/api/v1/users/{id}/unlock",post(UserHandler::unlock).require("user", Type::UPDATE))
+    .route("/api/v1/roles",get(RoleHandler::list).require("role", Type::READ))
+    .route("/api/v1/roles",post(RoleHandler::create).require("role", Type::CREATE))
+    .route("/api/v1/roles/{id}",get(RoleHandler::get).require("role", Type::READ))
+    .route("/api/v1/roles/{id}",put(RoleHandler::update).require("role", Type::UPDATE))
+    .route("/api/v1/roles/{id}",delete(RoleHandler::delete).require("role", Type::DELETE))
+    .route("/api/v1/roles/{id}/permissions",get(RoleHandler::list_permissions).require("role", Type::READ))
+    .route("/api/v1/roles/{id}/effective-permissions",get(RoleHandler::list_effective_permissions).require("role", Type::READ))
+    .route("/api/v1/roles/{id}/permissions/{permission_id}",put(RoleHandler::add_permission).require("role", Type::UPDATE))
+    .route("/api/v1/roles/{id}/permissions/{permission_id}",delete(RoleHandler::remove_permission).require("role", Type::UPDATE))
+    .route("/api/v1/roles/{id}/members",get(RoleHandler::list_members).require("role", Type::READ))
+    .route("/api/v1/roles/{id}/members",post(RoleHandler::add_members).require("role", Type::UPDATE))
+    .route("/api/v1/roles/{id}/mfa",put(MfaHandler::require_for_role).require("role", Type::UPDATE))
+    .route("/api/v1/permissions",get(PermissionHandler::list).require("permission", Type::READ))
+    .route("/api/v1/permissions",post(PermissionHandler::create).require("permission", Type::CREATE))
+    .route("/api/v1/permissions/{id}",get(PermissionHandler::get).require("permission", Type::READ))
+    .route("/api/v1/permissions/{id}",put(PermissionHandler::update).require("permission", Type::UPDATE))
+    .route("/api/v1/permissions/{id}",delete(PermissionHandler::delete).require("permission", Type::DELETE))
+    .route("/api/v1/actions",get(PermissionHandler::list_actions).require("action", Type::READ))
+    .route("/api/v1/actions",post(PermissionHandler::create_action).require("action", Type::CREATE))
+    .route("/api/v1/actions/{id}",put(PermissionHandler::update_action).require("action", Type::UPDATE))
+    .route("/api/v1/actions/{id}",delete(PermissionHandler::delete_action).require("action", Type::DELETE))
+    .route("/api/v1/service-accounts",post(ApiKeyHandler::create_service_account).require("service-account", Type::CREATE))
+    .route("/api/v1/service-accounts/{id}/tokens",get(ApiKeyHandler::list_of_service_account).require("service-account", Type::READ))
+    .route("/api/v1/service-accounts/{id}/tokens",post(ApiKeyHandler::create_for_service_account).require("service-account", Type::UPDATE))
+    .route("/api/v1/service-accounts/{id}/tokens/{token_id}",delete(ApiKeyHandler::revoke_of_service_account).require("service-account", Type::UPDATE))
+    .build()
 }
 
+// BOOTSTRAP_ADMIN gives a fresh deployment its first administrator
+async fn bootstrap_admin(state: &AppState){
+    let config=crate::config::BootstrapConfig::from_env();
+    if config.admin.is_empty() {
+        return;
+    }
+    match state.role_service.bootstrap_admin(&config.admin).await {
+        Ok(true) => tracing::info!("Granted the admin role to {}", config.admin),
+        Ok(false) => tracing::info!("The admin role is already held, BOOTSTRAP_ADMIN can be removed"),
+        Err(e) => tracing::error!("Can't grant the admin role to {}: {}", config.admin, e.message),
+    }
+}
 
 pub async fn start(){
     let state=Arc::new(AppState::new());
+    bootstrap_admin(&state).await;
     let app= app_routes(State(state));
 
     let listener= tokio::net::TcpListener::bind("127.0.0.1:8086")
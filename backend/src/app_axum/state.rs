@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use chrono::{DateTime, NaiveDateTime, TimeZone};
 
-use crate::{application::{auth_service::{self, AuthService, AuthServiceImpl}, user_service::{UserService, UserServiceImpl}}, diesel_impl::pool::{db_pool, DbConn}, domain::{permission::repo::PermissionRepo, role::repo::RoleRepo, security::repo::SecurityService, user::repo::UserRepo}};
+use crate::{application::{account_service::{AccountService, AccountServiceImpl}, api_key_service::{ApiKeyService, ApiKeyServiceImpl}, authorization_service::{AuthorizationService, AuthorizationServiceImpl}, auth_service::{self, AuthService, AuthServiceImpl}, mfa_service::{MfaService, MfaServiceImpl}, permission_service::{PermissionService, PermissionServiceImpl}, profile_service::{ProfileService, ProfileServiceImpl}, role_service::{RoleService, RoleServiceImpl}, session_service::{SessionService, SessionServiceImpl}, user_service::{UserService, UserServiceImpl}}, diesel_impl::pool::{db_pool, DbConn}, domain::{audit::repo::AuditRepo, menu::repo::MenuRepo, permission::repo::{ActionRepo, PermissionRepo}, role::repo::RoleRepo, mail::mailer::mailer_from_config, security::{action_token::ActionTokenRepo, api_key::ApiKeyRepo, keys::KeyRing, password_history::PasswordHistoryRepo, password_policy::PasswordPolicy, login_attempt::LoginAttemptRepo, mfa::MfaRepo, repo::SecurityService, token::TokenRepo}, user::repo::UserRepo}};
 
 
 #[derive(Clone)]
@@ -10,23 +10,64 @@ pub struct AppState{
     pub auth_service: Arc<dyn AuthService>,
     pub security_service: Arc<dyn SecurityService>,
     pub user_service: Arc<dyn UserService>,
+    pub session_service: Arc<dyn SessionService>,
+    pub mfa_service: Arc<dyn MfaService>,
+    pub account_service: Arc<dyn AccountService>,
+    pub api_key_service: Arc<dyn ApiKeyService>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
+    pub role_service: Arc<dyn RoleService>,
+    pub permission_service: Arc<dyn PermissionService>,
+    pub profile_service: Arc<dyn ProfileService>,
 }
 
 impl AppState {
     pub fn new()->AppState{
         let pool=Arc::new(db_pool());
+        let key_ring=KeyRing::load(&crate::config::JwtConfig::from_env()).expect("Failed to load JWT keys");
         let user_repo: Arc<dyn UserRepo> = Arc::new(crate::diesel_impl::user::UserDieselImpl::new(pool.clone()));
-        let security_service: Arc<dyn SecurityService> = Arc::new(crate::domain::security::repo::SecurityServiceImpl::new());
+        let security_service: Arc<dyn SecurityService> = Arc::new(crate::domain::security::repo::SecurityServiceImpl::new(key_ring, crate::config::HashConfig::from_env(), crate::config::TokenConfig::from_env()));
         let role_repo: Arc<dyn RoleRepo>=Arc::new(crate::diesel_impl::role::RoleDieselImpl::new(pool.clone()));
         let permission_repo: Arc<dyn PermissionRepo>=Arc::new(crate::diesel_impl::permission::PermissionDieselImpl::new(pool.clone()));
+        let token_repo: Arc<dyn TokenRepo>=Arc::new(crate::diesel_impl::token::TokenDieselImpl::new(pool.clone()));
 
-        let auth_service: Arc<dyn AuthService>=Arc::new(AuthServiceImpl::new(user_repo.clone(), security_service.clone()));
-        let user_service: Arc<dyn UserService>=Arc::new(UserServiceImpl::new(user_repo.clone(), role_repo, permission_repo));
+        let login_attempt_repo: Arc<dyn LoginAttemptRepo>=Arc::new(crate::diesel_impl::login_attempt::LoginAttemptDieselImpl::new(pool.clone()));
+
+        let mfa_repo: Arc<dyn MfaRepo>=Arc::new(crate::diesel_impl::mfa::MfaDieselImpl::new(pool.clone()));
+
+        let mfa_service: Arc<dyn MfaService>=Arc::new(MfaServiceImpl::new(mfa_repo, role_repo.clone(), crate::config::MfaConfig::from_env()));
+        let action_token_repo: Arc<dyn ActionTokenRepo>=Arc::new(crate::diesel_impl::action_token::ActionTokenDieselImpl::new(pool.clone()));
+        let mail_config=crate::config::MailConfig::from_env();
+        let mailer=mailer_from_config(&mail_config).expect("Failed to set up the mailer");
+
+        let password_history_repo: Arc<dyn PasswordHistoryRepo>=Arc::new(crate::diesel_impl::password_history::PasswordHistoryDieselImpl::new(pool.clone()));
+        let password_policy=Arc::new(PasswordPolicy::new(crate::config::PasswordPolicyConfig::from_env()));
+
+        let account_service: Arc<dyn AccountService>=Arc::new(AccountServiceImpl::new(user_repo.clone(), token_repo.clone(), action_token_repo.clone(), security_service.clone(), password_history_repo, mailer, password_policy, mail_config, crate::config::AccountConfig::from_env()));
+        let auth_service: Arc<dyn AuthService>=Arc::new(AuthServiceImpl::new(user_repo.clone(), token_repo.clone(), login_attempt_repo, action_token_repo, security_service.clone(), mfa_service.clone(), account_service.clone(), crate::config::TokenConfig::from_env(), crate::config::LoginThrottleConfig::from_env(), crate::config::MfaConfig::from_env(), crate::config::AccountConfig::from_env()));
+        let api_key_repo: Arc<dyn ApiKeyRepo>=Arc::new(crate::diesel_impl::api_key::ApiKeyDieselImpl::new(pool.clone()));
+        let audit_repo: Arc<dyn AuditRepo>=Arc::new(crate::diesel_impl::audit::AuditDieselImpl::new(pool.clone()));
+        let authorization_service: Arc<dyn AuthorizationService>=Arc::new(AuthorizationServiceImpl::new(role_repo.clone(), permission_repo.clone(), user_repo.clone(), audit_repo, crate::config::AuthorizationConfig::from_env()));
+        let api_key_service: Arc<dyn ApiKeyService>=Arc::new(ApiKeyServiceImpl::new(api_key_repo, user_repo.clone(), authorization_service.clone()));
+        let role_service: Arc<dyn RoleService>=Arc::new(RoleServiceImpl::new(role_repo.clone(), permission_repo.clone(), user_repo.clone(), authorization_service.clone()));
+        let action_repo: Arc<dyn ActionRepo>=Arc::new(crate::diesel_impl::action::ActionDieselImpl::new(pool.clone()));
+        let permission_service: Arc<dyn PermissionService>=Arc::new(PermissionServiceImpl::new(permission_repo.clone(), action_repo, authorization_service.clone()));
+        let menu_repo: Arc<dyn MenuRepo>=Arc::new(crate::diesel_impl::menu::MenuDieselImpl::new(pool.clone()));
+        let profile_service: Arc<dyn ProfileService>=Arc::new(ProfileServiceImpl::new(user_repo.clone(), role_repo.clone(), menu_repo, mfa_service.clone(), authorization_service.clone()));
+        let user_service: Arc<dyn UserService>=Arc::new(UserServiceImpl::new(user_repo.clone(), role_repo, token_repo.clone(), security_service.clone(), account_service.clone(), authorization_service.clone()));
+        let session_service: Arc<dyn SessionService>=Arc::new(SessionServiceImpl::new(token_repo));
 
         AppState{
             auth_service,
             security_service,
             user_service,
+            session_service,
+            mfa_service,
+            account_service,
+            api_key_service,
+            authorization_service,
+            role_service,
+            permission_service,
+            profile_service,
         }
     }
 }
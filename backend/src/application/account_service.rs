@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::config::{AccountConfig, MailConfig};
+use crate::domain::error::{CommonError, FieldError};
+use crate::domain::mail::mailer::{Email, Mailer};
+use crate::domain::security::action_token::{ActionPurpose, ActionTokenRepo};
+use crate::domain::security::password_history::PasswordHistoryRepo;
+use crate::domain::security::password_policy::PasswordPolicy;
+use crate::domain::security::repo::{random_token, sha256_hex, SecurityService};
+use crate::domain::security::token::TokenRepo;
+use crate::domain::user::repo::{User, UserRepo};
+
+#[async_trait]
+pub trait AccountService:Sync + Send {
+    // never tells whether `email` belongs to an account
+    async fn request_password_reset(&self, email: &str) -> Result<(), CommonError>;
+    // sets the new password and ends every session of the user
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), CommonError>;
+    async fn send_verification(&self, user: &User) -> Result<(), CommonError>;
+    // never tells whether `email` belongs to an account
+    async fn resend_verification(&self, email: &str) -> Result<(), CommonError>;
+    async fn verify_email(&self, token: &str) -> Result<(), CommonError>;
+    // checks `password` against the policy and, for an existing `user`, its recent passwords.
+    // violations are reported as field errors on `field`
+    async fn check_password(&self, field: &str, password: &str, username: &str, email: &str, user: Option<&User>) -> Result<(), CommonError>;
+    // adds a newly set hash to the password history
+    async fn remember_password(&self, user_id: i32, password_hash: String) -> Result<(), CommonError>;
+    // ends every other session of the user, `session_id` is kept
+    async fn change_password(&self, user_id: i32, session_id: &str, current_password: &str, new_password: &str) -> Result<(), CommonError>;
+}
+
+#[derive(Clone)]
+pub struct AccountServiceImpl{
+    pub user_repo: Arc<dyn UserRepo>,
+    pub token_repo: Arc<dyn TokenRepo>,
+    pub action_token_repo: Arc<dyn ActionTokenRepo>,
+    pub security_service: Arc<dyn SecurityService>,
+    pub password_history_repo: Arc<dyn PasswordHistoryRepo>,
+    pub mailer: Arc<dyn Mailer>,
+    pub password_policy: Arc<PasswordPolicy>,
+    pub mail_config: MailConfig,
+    pub config: AccountConfig,
+}
+
+fn invalid_token() -> CommonError {
+    CommonError::new("Token is invalid or expired", 400)
+}
+
+impl AccountServiceImpl {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(user_repo: Arc<dyn UserRepo>, token_repo: Arc<dyn TokenRepo>, action_token_repo: Arc<dyn ActionTokenRepo>, security_service: Arc<dyn SecurityService>, password_history_repo: Arc<dyn PasswordHistoryRepo>, mailer: Arc<dyn Mailer>, password_policy: Arc<PasswordPolicy>, mail_config: MailConfig, config: AccountConfig)-> Self{
+        Self { user_repo, token_repo, action_token_repo, security_service, password_history_repo, mailer, password_policy, mail_config, config }
+    }
+
+    // stores the hash of a new single-use token and returns the raw one for the link
+    async fn issue_token(&self, user_id: i32, purpose: ActionPurpose, ttl: i64) -> Result<String, CommonError> {
+        let token=random_token();
+        let expires_at=chrono::Utc::now().naive_utc()+chrono::Duration::seconds(ttl);
+        self.action_token_repo.create(user_id, purpose, sha256_hex(&token), expires_at).await.map_err(|e|e.into())?;
+        Ok(token)
+    }
+
+    // true when `password` matches the current or one of the remembered hashes
+    async fn recently_used(&self, user: &User, password: &str) -> Result<bool, CommonError> {
+        let history_size=self.password_policy.config.history_size;
+        if history_size <= 0 {
+            return Ok(false);
+        }
+        let mut hashes=self.password_history_repo.get_recent(user.id, history_size).await.map_err(|e|e.into())?;
+        hashes.push(user.password_hash.clone());
+        for hash in hashes {
+            if self.security_service.verify_hash(&hash, password).await.unwrap_or(false) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn set_password(&self, user_id: i32, password: &str) -> Result<(), CommonError> {
+        let password_hash=self.security_service.hash(password).await?;
+        self.user_repo.update_password(user_id, password_hash.clone()).await.map_err(|e|e.into())?;
+        self.remember_password(user_id, password_hash).await
+    }
+
+    fn link(&self, path: &str, token: &str) -> String {
+        format!("{}/{}?token={}", self.mail_config.app_url.trim_end_matches('/'), path, token)
+    }
+
+    // looks the user up by email for the unauthenticated endpoints, inactive and service accounts count as unknown
+    async fn active_user_by_email(&self, email: &str) -> Option<User> {
+        let user=self.user_repo.get_by_email_or_username(email.trim().to_string()).await.ok()?;
+        (user.is_active && !user.is_service_account && user.email.eq_ignore_ascii_case(email.trim())).then_some(user)
+    }
+}
+
+#[async_trait]
+impl AccountService for AccountServiceImpl {
+    async fn request_password_reset(&self, email: &str) -> Result<(), CommonError>{
+        let Some(user)=self.active_user_by_email(email).await else {
+            return Ok(());
+        };
+        let token=self.issue_token(user.id, ActionPurpose::PasswordReset, self.config.password_reset_ttl).await?;
+        let email=Email {
+            to: user.email.clone(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hi {},\n\nopen the link below to choose a new password. It is valid for {} minutes and can only be used once.\n\n{}\n\nIf you did not ask for this, you can ignore this email.\n",
+                user.username,
+                self.config.password_reset_ttl / 60,
+                self.link("reset-password", &token),
+            ),
+        };
+        // a mail failure must not tell the caller the account exists
+        if let Err(e)=self.mailer.send(email).await{
+            tracing::error!("Can't send password reset email to user {}: {}", user.id, e.message);
+        }
+        Ok(())
+    }
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<(), CommonError>{
+        // the link stays usable until a password is accepted, a rejected one doesn't burn it
+        let user_id=self.action_token_repo.find(sha256_hex(token), ActionPurpose::PasswordReset).await.map_err(|e|e.into())?
+            .ok_or_else(invalid_token)?;
+
+        let user=self.user_repo.get_by_id(user_id).await.map_err(|e|e.into())?;
+        self.check_password("new_password", new_password, &user.username, &user.email, Some(&user)).await?;
+
+        let password_hash=self.security_service.hash(new_password).await?;
+        self.action_token_repo.consume_for_password(sha256_hex(token), password_hash.clone()).await.map_err(|e|e.into())?
+            .ok_or_else(invalid_token)?;
+        self.remember_password(user_id, password_hash).await?;
+        self.token_repo.revoke_by_user_id(user_id).await.map_err(|e|e.into())?;
+        self.user_repo.reset_failed_logins(user_id).await.map_err(|e|e.into())?;
+        // the link was delivered to the address, so it is verified as well
+        self.user_repo.set_email_verified(user_id).await.map_err(|e|e.into())?;
+        Ok(())
+    }
+    async fn send_verification(&self, user: &User) -> Result<(), CommonError>{
+        if user.email_verified {
+            return Ok(());
+        }
+        let token=self.issue_token(user.id, ActionPurpose::EmailVerification, self.config.email_verification_ttl).await?;
+        let email=Email {
+            to: user.email.clone(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Hi {},\n\nopen the link below to verify your email address. It is valid for {} hours.\n\n{}\n",
+                user.username,
+                self.config.email_verification_ttl / 3600,
+                self.link("verify-email", &token),
+            ),
+        };
+        self.mailer.send(email).await
+    }
+    async fn resend_verification(&self, email: &str) -> Result<(), CommonError>{
+        let Some(user)=self.active_user_by_email(email).await else {
+            return Ok(());
+        };
+        if let Err(e)=self.send_verification(&user).await{
+            tracing::error!("Can't send verification email to user {}: {}", user.id, e.message);
+        }
+        Ok(())
+    }
+    async fn verify_email(&self, token: &str) -> Result<(), CommonError>{
+        let user_id=self.action_token_repo.consume(sha256_hex(token), ActionPurpose::EmailVerification).await.map_err(|e|e.into())?
+            .ok_or_else(invalid_token)?;
+        self.user_repo.set_email_verified(user_id).await.map_err(|e|e.into())
+    }
+    async fn check_password(&self, field: &str, password: &str, username: &str, email: &str, user: Option<&User>) -> Result<(), CommonError>{
+        let mut errors=self.password_policy.check(field, password, username, email);
+        // hashing against the history is costly, only do it for otherwise valid passwords
+        if let Some(user)=user.filter(|_| errors.is_empty())
+            && self.recently_used(user, password).await? {
+            errors.push(FieldError::new(field, "recently_used", "Password was used recently, choose a different one"));
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(CommonError::validation(errors))
+    }
+    async fn remember_password(&self, user_id: i32, password_hash: String) -> Result<(), CommonError>{
+        let history_size=self.password_policy.config.history_size;
+        if history_size <= 0 {
+            return Ok(());
+        }
+        self.password_history_repo.add(user_id, password_hash, history_size).await.map_err(|e|e.into())
+    }
+    async fn change_password(&self, user_id: i32, session_id: &str, current_password: &str, new_password: &str) -> Result<(), CommonError>{
+        let user=self.user_repo.get_by_id(user_id).await.map_err(|e|e.into())?;
+        if !self.security_service.verify_hash(&user.password_hash, current_password).await? {
+            return Err(CommonError::validation(vec![FieldError::new("current_password", "invalid", "Current password is wrong")]));
+        }
+        self.check_password("new_password", new_password, &user.username, &user.email, Some(&user)).await?;
+
+        self.set_password(user_id, new_password).await?;
+        self.token_repo.revoke_other_sessions(user_id, session_id.to_string()).await.map_err(|e|e.into())?;
+        Ok(())
+    }
+}
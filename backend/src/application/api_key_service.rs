@@ -0,0 +1,157 @@
+use std::{collections::HashSet, sync::Arc};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::Serialize;
+
+use crate::domain::cursor::check_page_request;
+use crate::domain::error::{CommonError, FieldError};
+use crate::application::authorization_service::AuthorizationService;
+use crate::domain::permission::repo::scope_key;
+use crate::domain::security::api_key::{ApiKey, ApiKeyRepo, NewApiKey, API_KEY_PREFIX};
+use crate::domain::security::repo::{random_token, sha256_hex};
+use crate::domain::user::repo::{User, UserIdentity, UserRepo};
+
+// characters of the secret kept in `prefix`
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub key: ApiKey,
+    // only returned here, it can't be read again later
+    pub secret: String,
+}
+
+#[async_trait]
+pub trait ApiKeyService:Sync + Send {
+    async fn list(&self, user_id: i32, page: PageRequest) -> Result<PageData<ApiKey>, CommonError>;
+    // `scopes` must be "resource:ACTION" pairs the user currently holds
+    async fn create(&self, user_id: i32, name: String, scopes: Vec<String>, expires_at: Option<NaiveDateTime>) -> Result<CreatedApiKey, CommonError>;
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<(), CommonError>;
+    async fn authenticate(&self, secret: &str) -> Result<UserIdentity, CommonError>;
+    async fn create_service_account(&self, username: String, email: Option<String>) -> Result<User, CommonError>;
+    // fails unless `user_id` is a service account
+    async fn get_service_account(&self, user_id: i32) -> Result<User, CommonError>;
+}
+
+#[derive(Clone)]
+pub struct ApiKeyServiceImpl{
+    pub api_key_repo: Arc<dyn ApiKeyRepo>,
+    pub user_repo: Arc<dyn UserRepo>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
+}
+
+fn invalid_key() -> CommonError {
+    CommonError::new("API key is invalid or expired", 401)
+}
+
+impl ApiKeyServiceImpl {
+    pub fn new(api_key_repo: Arc<dyn ApiKeyRepo>, user_repo: Arc<dyn UserRepo>, authorization_service: Arc<dyn AuthorizationService>)-> Self{
+        Self { api_key_repo, user_repo, authorization_service }
+    }
+
+    // every "resource:ACTION" the user holds through its roles
+    async fn user_scopes(&self, user_id: i32) -> Result<HashSet<String>, CommonError> {
+        let permissions=self.authorization_service.get_permissions(user_id).await?;
+        Ok(permissions
+            .iter()
+            .flat_map(|permission| permission.action.iter().map(|action| scope_key(&permission.resource, &action.key)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ApiKeyService for ApiKeyServiceImpl {
+    async fn list(&self, user_id: i32, page: PageRequest) -> Result<PageData<ApiKey>, CommonError>{
+        check_page_request(&page, "-id")?;
+        self.api_key_repo.get_by_user_id(user_id, page).await.map_err(|e|e.into())
+    }
+    async fn create(&self, user_id: i32, name: String, scopes: Vec<String>, expires_at: Option<NaiveDateTime>) -> Result<CreatedApiKey, CommonError>{
+        let name=name.trim().to_string();
+        let mut scopes: Vec<String>=scopes.into_iter().map(|scope| scope.trim().to_string()).collect();
+        scopes.sort();
+        scopes.dedup();
+
+        let mut errors=Vec::new();
+        if name.is_empty() || name.chars().count() > 100 {
+            errors.push(FieldError::new("name", "invalid_length", "Name must be between 1 and 100 characters long"));
+        }
+        if scopes.is_empty() {
+            errors.push(FieldError::new("scopes", "required", "At least one scope is required"));
+        }
+        let held=self.user_scopes(user_id).await?;
+        let missing: Vec<&str>=scopes.iter().filter(|scope| !held.contains(*scope)).map(|scope| scope.as_str()).collect();
+        if !missing.is_empty() {
+            errors.push(FieldError::new("scopes", "not_permitted", format!("You don't have {}", missing.join(", "))));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now().naive_utc()) {
+            errors.push(FieldError::new("expires_at", "in_past", "Expiry must be in the future"));
+        }
+        if !errors.is_empty() {
+            return Err(CommonError::validation(errors));
+        }
+
+        let secret=format!("{}{}", API_KEY_PREFIX, random_token());
+        let key=self.api_key_repo.create(NewApiKey {
+            user_id,
+            name,
+            prefix: secret.chars().take(DISPLAY_PREFIX_LEN).collect(),
+            key_hash: sha256_hex(&secret),
+            scopes,
+            expires_at,
+        }).await.map_err(|e|e.into())?;
+
+        Ok(CreatedApiKey { key, secret })
+    }
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<(), CommonError>{
+        let revoked=self.api_key_repo.revoke(user_id, id).await.map_err(|e|e.into())?;
+        if revoked == 0 {
+            return Err(CommonError::new("API key not found", 404));
+        }
+        Ok(())
+    }
+    async fn authenticate(&self, secret: &str) -> Result<UserIdentity, CommonError>{
+        let key=self.api_key_repo.get_by_hash(sha256_hex(secret)).await.map_err(|e|e.into())?
+            .ok_or_else(invalid_key)?;
+        let expired=key.expires_at.is_some_and(|expires_at| expires_at < chrono::Utc::now().naive_utc());
+        if key.revoked || expired {
+            return Err(invalid_key());
+        }
+        let user=self.user_repo.get_by_id(key.user_id).await.map_err(|_| invalid_key())?;
+        if !user.is_active {
+            return Err(invalid_key());
+        }
+        if let Err(e)=self.api_key_repo.touch(key.id).await{
+            tracing::warn!("Can't update last_used_at of API key {}: {}", key.id, e.message);
+        }
+
+        Ok(UserIdentity {
+            email: user.email,
+            user_id: user.id,
+            token_id: 0,
+            session_id: String::new(),
+            api_key_id: Some(key.id),
+            scopes: Some(key.scopes),
+            permissions_version: None,
+        })
+    }
+    async fn create_service_account(&self, username: String, email: Option<String>) -> Result<User, CommonError>{
+        let username=username.trim().to_string();
+        if username.is_empty() || username.chars().count() > 50 {
+            return Err(CommonError::validation(vec![FieldError::new("username", "invalid_length", "Username must be between 1 and 50 characters long")]));
+        }
+        // the column is required, but nothing is ever sent to a service account
+        let email=email.unwrap_or_else(|| format!("{}@service-account.invalid", username));
+        self.user_repo.create_service_account(username, email).await.map_err(|e|e.into())
+    }
+    async fn get_service_account(&self, user_id: i32) -> Result<User, CommonError>{
+        let user=self.user_repo.get_by_id(user_id).await
+            .map_err(|_| CommonError::new("Service account not found", 404))?;
+        if !user.is_service_account {
+            return Err(CommonError::new("Service account not found", 404));
+        }
+        Ok(user)
+    }
+}
@@ -1,60 +1,357 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use tokio::sync::OnceCell;
 
-use crate::domain::{user::repo::{User,UserRepo}};
+use crate::application::account_service::AccountService;
+use crate::application::mfa_service::{MfaEnrollment, MfaService};
+use crate::config::{AccountConfig, LoginThrottleConfig, MfaConfig, TokenConfig};
+use crate::domain::{user::repo::{User,UserIdentity,UserRepo}};
 use crate::domain::error::CommonError;
 
-use crate::domain::security::repo::{SecurityService, Token};
+use crate::domain::security::action_token::{ActionPurpose, ActionTokenRepo};
+use crate::domain::security::repo::{random_token, sha256_hex, SecurityService};
+use crate::domain::security::login_attempt::LoginAttemptRepo;
+use crate::domain::security::token::{ClientInfo, NewUserToken, TokenPair, TokenRepo, TokenType};
 
 
 
 
+pub enum LoginOutcome {
+    Authenticated { user: User, tokens: TokenPair },
+    // the password was right, `mfa_token` must be exchanged at /login/mfa together with a code
+    MfaRequired { mfa_token: String, enrollment_required: bool },
+}
+
 #[async_trait]
 pub trait AuthService:Sync + Send {
-    async fn login(&self, email_or_username: &str, password: &str) -> Result<(User, String), CommonError>;
-    async fn logout(&self,user_id:i32);
-    async fn register(&self, username: String, email: String, password: String)-> Result<(User,String), CommonError>;
+    async fn login(&self, email_or_username: &str, password: &str, client: ClientInfo) -> Result<LoginOutcome, CommonError>;
+    // second login step, returns recovery codes when it also finished a required enrollment
+    async fn login_mfa(&self, mfa_token: &str, code: &str, client: ClientInfo) -> Result<(User, TokenPair, Option<Vec<String>>), CommonError>;
+    // enrollment for users whose role requires 2FA but who never set it up
+    async fn enroll_mfa(&self, mfa_token: &str) -> Result<MfaEnrollment, CommonError>;
+    async fn logout(&self, identity: &UserIdentity) -> Result<(), CommonError>;
+    async fn register(&self, username: String, email: String, password: String, client: ClientInfo)-> Result<(User,TokenPair), CommonError>;
+    async fn refresh(&self, refresh_token: &str, client: ClientInfo) -> Result<TokenPair, CommonError>;
+    async fn authenticate(&self, token: &str) -> Result<UserIdentity, CommonError>;
 }
 
 
 #[derive(Clone)]
 pub struct AuthServiceImpl{
   pub user_repo: Arc<dyn UserRepo>,
-  pub security_service: Arc<dyn SecurityService>
+  pub token_repo: Arc<dyn TokenRepo>,
+  pub login_attempt_repo: Arc<dyn LoginAttemptRepo>,
+  pub action_token_repo: Arc<dyn ActionTokenRepo>,
+  pub security_service: Arc<dyn SecurityService>,
+  pub mfa_service: Arc<dyn MfaService>,
+  pub account_service: Arc<dyn AccountService>,
+  pub token_config: TokenConfig,
+  pub throttle_config: LoginThrottleConfig,
+  pub mfa_config: MfaConfig,
+  pub account_config: AccountConfig,
+  // hash checked when there is no real one, so every failed login costs the same
+  pub dummy_hash: Arc<OnceCell<String>>,
+}
+
+fn invalid_mfa_token() -> CommonError {
+    CommonError::new("Two-factor session is invalid or expired", 401)
+}
+
+// same answer for unknown user, wrong password, locked or inactive account
+fn invalid_credentials() -> CommonError {
+    CommonError::new("Invalid credentials", 401)
 }
 
 impl AuthServiceImpl {
-    pub fn new(user_repo: Arc<dyn UserRepo>, security: Arc<dyn SecurityService>)-> Self{
-        Self { user_repo, security_service:security }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(user_repo: Arc<dyn UserRepo>, token_repo: Arc<dyn TokenRepo>, login_attempt_repo: Arc<dyn LoginAttemptRepo>, action_token_repo: Arc<dyn ActionTokenRepo>, security: Arc<dyn SecurityService>, mfa_service: Arc<dyn MfaService>, account_service: Arc<dyn AccountService>, token_config: TokenConfig, throttle_config: LoginThrottleConfig, mfa_config: MfaConfig, account_config: AccountConfig)-> Self{
+        Self { user_repo, token_repo, login_attempt_repo, action_token_repo, security_service:security, mfa_service, account_service, token_config, throttle_config, mfa_config, account_config, dummy_hash: Arc::new(OnceCell::new()) }
+    }
+
+    async fn record_attempt(&self, user_id: Option<i32>, identifier: &str, client: &ClientInfo, succeeded: bool) {
+        if let Err(e)=self.login_attempt_repo.record(user_id, identifier.to_string(), client.ip_address.clone(), succeeded).await{
+            tracing::warn!("Can't record login attempt: {}", e.message);
+        }
+    }
+
+    // burns the time of a password check, otherwise fast answers would tell which accounts exist
+    async fn verify_dummy_hash(&self, password: &str) -> Result<(), CommonError> {
+        let hashed=self.dummy_hash.get_or_try_init(|| async { self.security_service.hash(&random_token()).await }).await?;
+        self.security_service.verify_hash(hashed, password).await?;
+        Ok(())
+    }
+
+    // records the failure and waits longer the more failures there were before answering
+    async fn reject_login(&self, user_id: Option<i32>, identifier: &str, client: &ClientInfo, failures: i64) -> CommonError {
+        self.record_attempt(user_id, identifier, client, false).await;
+        tokio::time::sleep(self.throttle_config.delay(failures)).await;
+        invalid_credentials()
+    }
+
+    // counts a wrong password or code against the account, locking it at the threshold
+    async fn reject_user_login(&self, user: &User, identifier: &str, client: &ClientInfo, ip_failures: i64) -> Result<CommonError, CommonError> {
+        let count=self.user_repo.increment_failed_logins(user.id).await.map_err(|e|e.into())?;
+        if count >= self.throttle_config.max_failed_attempts {
+            let until=chrono::Utc::now().naive_utc()+chrono::Duration::seconds(self.throttle_config.lockout_seconds);
+            self.user_repo.lock(user.id, until).await.map_err(|e|e.into())?;
+            tracing::warn!("User {} locked until {} after {} failed logins", user.id, until, count);
+        }
+        let failures=ip_failures.max(count as i64) + 1;
+        Ok(self.reject_login(Some(user.id), identifier, client, failures).await)
+    }
+
+    async fn create_mfa_token(&self, user: &User) -> Result<String, CommonError> {
+        let token=random_token();
+        let expires_at=chrono::Utc::now().naive_utc()+chrono::Duration::seconds(self.mfa_config.pending_token_ttl);
+        self.action_token_repo.create(user.id, ActionPurpose::MfaLogin, sha256_hex(&token), expires_at).await.map_err(|e|e.into())?;
+        Ok(token)
     }
+
+    // user behind a still valid "mfa pending" token
+    async fn mfa_pending_user(&self, mfa_token: &str) -> Result<User, CommonError> {
+        let user_id=self.action_token_repo.find(sha256_hex(mfa_token), ActionPurpose::MfaLogin).await.map_err(|e|e.into())?
+            .ok_or_else(invalid_mfa_token)?;
+        let user=self.user_repo.get_by_id(user_id).await.map_err(|_| invalid_mfa_token())?;
+        let locked=user.locked_until.is_some_and(|until| until > chrono::Utc::now().naive_utc());
+        if !user.is_active || locked {
+            return Err(invalid_credentials());
+        }
+        Ok(user)
+    }
+
+    async fn ip_failures(&self, client: &ClientInfo) -> Result<i64, CommonError> {
+        let Some(ip)=client.ip_address.clone() else {
+            return Ok(0);
+        };
+        let since=chrono::Utc::now().naive_utc()-chrono::Duration::seconds(self.throttle_config.ip_window_seconds);
+        self.login_attempt_repo.count_failures_by_ip(ip, since).await.map_err(|e|e.into())
+    }
+
+    // issues an access JWT and a refresh token in the given family and records both
+    async fn issue_tokens(&self, user: &User, family_id: String, client: ClientInfo) -> Result<TokenPair, CommonError> {
+        let (token, claims)=self.security_service.create_jwt(user, &family_id).await?;
+        let access_expires_at=chrono::DateTime::from_timestamp(claims.exp, 0)
+            .map(|v| v.naive_utc())
+            .unwrap_or_default();
+        self.token_repo
+            .create(NewUserToken {
+                user_id: user.id,
+                token_hash: sha256_hex(&token),
+                token_type: TokenType::Access,
+                family_id: family_id.clone(),
+                client: client.clone(),
+                expires_at: access_expires_at,
+            })
+            .await
+            .map_err(|e|e.into())?;
+
+        let refresh_token=random_token();
+        let refresh_expires_at=chrono::Utc::now().naive_utc()+chrono::Duration::seconds(self.token_config.refresh_token_ttl);
+        self.token_repo
+            .create(NewUserToken {
+                user_id: user.id,
+                token_hash: sha256_hex(&refresh_token),
+                token_type: TokenType::Refresh,
+                family_id,
+                client,
+                expires_at: refresh_expires_at,
+            })
+            .await
+            .map_err(|e|e.into())?;
+
+        Ok(TokenPair { token, refresh_token, expires_in: self.token_config.access_token_ttl })
+    }
+}
+
+fn new_family_id() -> String {
+    uuid::Uuid::new_v4().to_string()
 }
 
 #[async_trait]
 impl AuthService for AuthServiceImpl {
-    async fn login(&self, email_or_username: &str, password: &str) -> Result<(User, String), CommonError>{
-        let user= self.user_repo.get_by_email_or_username(email_or_username.to_string()).await.map_err(|e|e.into())?;
+    async fn login(&self, email_or_username: &str, password: &str, client: ClientInfo) -> Result<LoginOutcome, CommonError>{
+        let ip_failures=self.ip_failures(&client).await?;
+        if ip_failures >= self.throttle_config.ip_max_failed_attempts {
+            return Err(self.reject_login(None, email_or_username, &client, ip_failures + 1).await);
+        }
 
-        if !(self.security_service.hash(password).await?==user.password_hash){
-            return Err(CommonError { message:"Password or username is incorrected".to_string() , code: 1 });
+        let Ok(mut user)= self.user_repo.get_by_email_or_username(email_or_username.to_string()).await else {
+            self.verify_dummy_hash(password).await?;
+            return Err(self.reject_login(None, email_or_username, &client, ip_failures + 1).await);
+        };
+
+        let now=chrono::Utc::now().naive_utc();
+        match user.locked_until {
+            // locked accounts don't get their password checked, only the time of it
+            Some(until) if until > now => {
+                self.verify_dummy_hash(password).await?;
+                let failures=ip_failures.max(user.failed_login_count as i64) + 1;
+                return Err(self.reject_login(Some(user.id), email_or_username, &client, failures).await);
+            }
+            // the lock ran out, start counting again
+            Some(_) => {
+                self.user_repo.reset_failed_logins(user.id).await.map_err(|e|e.into())?;
+                user.failed_login_count=0;
+                user.locked_until=None;
+            }
+            None => {}
         }
-        let token=self.security_service.create_jwt(&user).await.map_err(|e|e.into())?;
-        //save token
 
-        Ok((user,token))
+        // service accounts have no password, they only use API keys. Imported users have none ("!")
+        // until they set one through a reset link
+        if user.is_service_account || user.password_hash == "!" {
+            self.verify_dummy_hash(password).await?;
+            return Err(self.reject_login(Some(user.id), email_or_username, &client, ip_failures + 1).await);
+        }
+        if !self.security_service.verify_hash(&user.password_hash, password).await?{
+            return Err(self.reject_user_login(&user, email_or_username, &client, ip_failures).await?);
+        }
+        if !user.is_active {
+            return Err(self.reject_login(Some(user.id), email_or_username, &client, ip_failures + 1).await);
+        }
+        if self.account_config.require_verified_email && !user.email_verified {
+            return Err(CommonError::new("Email address is not verified", 403));
+        }
+
+        // upgrade legacy SHA-256 hashes (or outdated argon2 costs) now that we know the raw password
+        if self.security_service.needs_rehash(&user.password_hash).await{
+            let password_hash=self.security_service.hash(password).await?;
+            if let Err(e)=self.user_repo.update_password(user.id, password_hash).await{
+                tracing::warn!("Can't rehash password of user {}: {}", user.id, e.message);
+            }
+        }
+        let mfa_enabled=self.mfa_service.is_enabled(user.id).await?;
+        if mfa_enabled || self.mfa_service.is_required(user.id).await? {
+            let mfa_token=self.create_mfa_token(&user).await?;
+            return Ok(LoginOutcome::MfaRequired { mfa_token, enrollment_required: !mfa_enabled });
+        }
+
+        if user.failed_login_count > 0 {
+            self.user_repo.reset_failed_logins(user.id).await.map_err(|e|e.into())?;
+            user.failed_login_count=0;
+        }
+        self.record_attempt(Some(user.id), email_or_username, &client, true).await;
+        let tokens=self.issue_tokens(&user, new_family_id(), client).await?;
+
+        Ok(LoginOutcome::Authenticated { user, tokens })
     }
-    async fn logout(&self,user_id:i32){
+    async fn login_mfa(&self, mfa_token: &str, code: &str, client: ClientInfo) -> Result<(User, TokenPair, Option<Vec<String>>), CommonError>{
+        let ip_failures=self.ip_failures(&client).await?;
+        let mut user=self.mfa_pending_user(mfa_token).await?;
+        // same limit as the password step, otherwise codes could be guessed from a blocked address
+        if ip_failures >= self.throttle_config.ip_max_failed_attempts {
+            return Err(self.reject_login(Some(user.id), &user.email, &client, ip_failures + 1).await);
+        }
 
-        //revoked token
-        todo!()
+        let recovery_codes=if self.mfa_service.is_enabled(user.id).await? {
+            if !self.mfa_service.verify(user.id, code).await? {
+                return Err(self.reject_user_login(&user, &user.email, &client, ip_failures).await?);
+            }
+            None
+        } else {
+            match self.mfa_service.confirm(user.id, code).await {
+                Ok(codes) => Some(codes),
+                Err(e) if e.code == 400 => {
+                    return Err(self.reject_user_login(&user, &user.email, &client, ip_failures).await?);
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        // one session per password check, a second request with the same token gets nothing
+        if self.action_token_repo.consume(sha256_hex(mfa_token), ActionPurpose::MfaLogin).await.map_err(|e|e.into())?.is_none() {
+            return Err(invalid_mfa_token());
+        }
+
+        if user.failed_login_count > 0 {
+            self.user_repo.reset_failed_logins(user.id).await.map_err(|e|e.into())?;
+            user.failed_login_count=0;
+        }
+        self.record_attempt(Some(user.id), &user.email, &client, true).await;
+        let tokens=self.issue_tokens(&user, new_family_id(), client).await?;
+
+        Ok((user, tokens, recovery_codes))
+    }
+    async fn enroll_mfa(&self, mfa_token: &str) -> Result<MfaEnrollment, CommonError>{
+        let user=self.mfa_pending_user(mfa_token).await?;
+        self.mfa_service.enroll(user.id, &user.email).await
     }
-    async fn register(&self, username: String, email: String, password: String)-> Result<(User,String), CommonError>{
+    async fn logout(&self, identity: &UserIdentity) -> Result<(), CommonError>{
+        if identity.api_key_id.is_some() {
+            return Err(CommonError::new("API keys have no session, revoke the key instead", 400));
+        }
+        // ends the whole session, so the refresh token can't bring it back
+        self.token_repo.revoke_family(identity.session_id.clone()).await.map_err(|e|e.into())?;
+        Ok(())
+    }
+    async fn register(&self, username: String, email: String, password: String, client: ClientInfo)-> Result<(User,TokenPair), CommonError>{
+        self.account_service.check_password("password", &password, &username, &email, None).await?;
+
         let password_hash=self.security_service.hash(&password).await.map_err(|e|e.into())?;
-        let user= self.user_repo.create(username,email,password_hash).await.map_err(|e|e.into())?;
+        let user= self.user_repo.create(username,email,password_hash.clone()).await.map_err(|e|e.into())?;
+        self.account_service.remember_password(user.id, password_hash).await?;
+        if let Err(e)=self.account_service.send_verification(&user).await{
+            tracing::error!("Can't send verification email to user {}: {}", user.id, e.message);
+        }
 
-        let token=self.security_service.create_jwt(&user).await.map_err(|e|e.into())?;
+        let tokens=self.issue_tokens(&user, new_family_id(), client).await?;
 
-        Ok((user, token))
+        Ok((user, tokens))
 
     }
-}
\ No newline at end of file
+    async fn refresh(&self, refresh_token: &str, client: ClientInfo) -> Result<TokenPair, CommonError>{
+        let invalid = || CommonError::new("Refresh token is invalid", 401);
+
+        let stored=self.token_repo.get_by_token(sha256_hex(refresh_token)).await.map_err(|_| invalid())?;
+        if stored.token_type != TokenType::Refresh {
+            return Err(invalid());
+        }
+        // a rotated refresh token is presented again: someone else holds a copy, end the session
+        if stored.revoked || !self.token_repo.revoke_if_active(stored.id).await.map_err(|e|e.into())? {
+            tracing::warn!("Refresh token reuse detected for user {}, revoking family {}", stored.user_id, stored.family_id);
+            self.token_repo.revoke_family(stored.family_id).await.map_err(|e|e.into())?;
+            return Err(invalid());
+        }
+        if stored.expires_at < chrono::Utc::now().naive_utc() {
+            return Err(CommonError::new("Refresh token is expired!", 401));
+        }
+
+        let user=self.user_repo.get_by_id(stored.user_id).await.map_err(|_| invalid())?;
+        if !user.is_active {
+            self.token_repo.revoke_family(stored.family_id).await.map_err(|e|e.into())?;
+            return Err(invalid());
+        }
+
+        self.issue_tokens(&user, stored.family_id, client).await
+    }
+    async fn authenticate(&self, token: &str) -> Result<UserIdentity, CommonError>{
+        self.security_service.verify_jwt(token.to_string()).await?;
+        let claim=self.security_service.decode(token).await?;
+
+        // a valid signature is not enough, the token must still be recorded and not revoked
+        let stored=self.token_repo.get_by_token(sha256_hex(token)).await
+            .map_err(|_| CommonError::new("Token is not recognized", 401))?;
+        if stored.token_type != TokenType::Access {
+            return Err(CommonError::new("Token is not recognized", 401));
+        }
+        if stored.revoked || stored.user_id as i64 != claim.claims.sub {
+            return Err(CommonError::new("Token has been revoked", 401));
+        }
+        if stored.expires_at < chrono::Utc::now().naive_utc() {
+            return Err(CommonError::new("Access token is expired!", 401));
+        }
+        if let Err(e)=self.token_repo.touch(stored.id).await{
+            tracing::warn!("Can't update last_used_at of token {}: {}", stored.id, e.message);
+        }
+
+        Ok(UserIdentity {
+            email: claim.claims.email,
+            user_id: stored.user_id,
+            token_id: stored.id,
+            session_id: stored.family_id,
+            api_key_id: None,
+            scopes: None,
+            permissions_version: Some(claim.claims.pv),
+        })
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use crate::config::AuthorizationConfig;
+use crate::domain::audit::repo::{AuditRepo, NewAuditLog};
+use crate::domain::error::CommonError;
+use crate::domain::permission::policy::Attributes;
+use crate::domain::permission::repo::{scope_key, Permission, PermissionRepo, Type};
+use crate::domain::role::repo::{role_chain, RoleRepo};
+use crate::domain::user::repo::{User, UserIdentity, UserRepo};
+
+// a permission check of one request
+#[derive(Debug, Clone)]
+pub struct AccessRequest {
+    pub resource: String,
+    pub action: Type,
+    // path parameters of the route
+    pub target: Map<String, Value>,
+    // the one of them that identifies the resource checked
+    pub target_id: Option<Value>,
+    pub method: String,
+    pub path: String,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub allowed: bool,
+    // shown in 403 responses and the audit log
+    pub reason: String,
+}
+
+impl Decision {
+    fn allow(reason: String) -> Self {
+        Self { allowed: true, reason }
+    }
+
+    fn deny(reason: String) -> Self {
+        Self { allowed: false, reason }
+    }
+}
+
+#[async_trait]
+pub trait AuthorizationService:Sync + Send {
+    // everything granted to the user through its roles and their ancestors
+    async fn get_permissions(&self, user_id: i32) -> Result<Vec<Permission>, CommonError>;
+    // the caller holds the permission, the policy attached to it (if any) holds for the request
+    // and, for API keys, the key was given that scope. Every decision is audited
+    async fn authorize(&self, identity: &UserIdentity, request: AccessRequest) -> Result<Decision, CommonError>;
+    // every "resource:ACTION" the caller may use, narrowed to the scopes of an API key
+    async fn get_grants(&self, identity: &UserIdentity) -> Result<Vec<String>, CommonError>;
+    // must be called by every write path that changes what the users are granted
+    async fn invalidate_users(&self, user_ids: Vec<i32>) -> Result<(), CommonError>;
+    // members of the roles and of every role inheriting from them
+    async fn invalidate_roles(&self, role_ids: Vec<i32>) -> Result<(), CommonError>;
+}
+
+struct CachedPermissions {
+    permissions: Arc<Vec<Permission>>,
+    // users.permissions_version the entry was loaded with
+    version: i32,
+    expires_at: Instant,
+}
+
+#[derive(Clone)]
+pub struct AuthorizationServiceImpl{
+    pub role_repo: Arc<dyn RoleRepo>,
+    pub permission_repo: Arc<dyn PermissionRepo>,
+    pub user_repo: Arc<dyn UserRepo>,
+    pub audit_repo: Arc<dyn AuditRepo>,
+    pub config: AuthorizationConfig,
+    cache: Arc<RwLock<HashMap<i32, CachedPermissions>>>,
+    // bumped by every invalidation, a load that raced with one is not cached
+    generation: Arc<AtomicU64>,
+}
+
+impl AuthorizationServiceImpl {
+    pub fn new(role_repo: Arc<dyn RoleRepo>, permission_repo: Arc<dyn PermissionRepo>, user_repo: Arc<dyn UserRepo>, audit_repo: Arc<dyn AuditRepo>, config: AuthorizationConfig)-> Self{
+        Self {
+            role_repo,
+            permission_repo,
+            user_repo,
+            audit_repo,
+            config,
+            cache: Arc::new(RwLock::new(HashMap::new())),
+            generation: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    // a token issued with a newer version than the entry means another instance changed the grants
+    fn cached(&self, user_id: i32, token_version: Option<i32>) -> Option<Arc<Vec<Permission>>> {
+        let cache = self.cache.read().unwrap_or_else(PoisonError::into_inner);
+        cache
+            .get(&user_id)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .filter(|entry| token_version.is_none_or(|version| version <= entry.version))
+            .map(|entry| entry.permissions.clone())
+    }
+
+    async fn load(&self, user_id: i32, token_version: Option<i32>) -> Result<Arc<Vec<Permission>>, CommonError> {
+        if let Some(permissions) = self.cached(user_id, token_version) {
+            return Ok(permissions);
+        }
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let version = self.user_repo.get_permissions_version(user_id).await.map_err(|e|e.into())?;
+        let held=self.role_repo.get_grants_by_user_id(user_id).await.map_err(|e|e.into())?;
+
+        // the entry must not outlive the first grant that expires
+        let mut ttl = Duration::from_secs(self.config.cache_ttl.max(0) as u64);
+        let now = chrono::Utc::now().naive_utc();
+        if let Some(expires_at) = held.iter().filter_map(|grant| grant.expires_at).min() {
+            ttl = ttl.min((expires_at - now).to_std().unwrap_or_default());
+        }
+
+        let mut permissions: Vec<Permission>=Vec::new();
+        if !held.is_empty() {
+            let roles=self.role_repo.get().await.map_err(|e|e.into())?;
+            let mut role_ids: Vec<i32>=Vec::new();
+            for grant in held {
+                for ancestor in role_chain(&roles, grant.role.id) {
+                    if !role_ids.contains(&ancestor.id) {
+                        role_ids.push(ancestor.id);
+                    }
+                }
+            }
+            for role_id in role_ids {
+                let granted=self.permission_repo.get_permissions_by_role_id(role_id).await.map_err(|e|e.into())?;
+                for permission in granted {
+                    if !permissions.iter().any(|p| p.id == permission.id) {
+                        permissions.push(permission);
+                    }
+                }
+            }
+        }
+
+        let permissions = Arc::new(permissions);
+        let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+        if generation == self.generation.load(Ordering::SeqCst) {
+            cache.insert(user_id, CachedPermissions { permissions: permissions.clone(), version, expires_at: Instant::now() + ttl });
+        }
+        Ok(permissions)
+    }
+
+    // subject.*, resource.* and context.* of a request, only built when a policy needs them
+    async fn attributes(&self, identity: &UserIdentity, request: &AccessRequest) -> Result<Attributes, CommonError> {
+        let user=self.user_repo.get_by_id(identity.user_id).await.map_err(|e|e.into())?;
+        let mut subject=user_attributes(&user);
+        if let Some(api_key_id)=identity.api_key_id {
+            subject.insert("api_key_id".to_string(), api_key_id.into());
+        }
+
+        let mut resource=request.target.clone();
+        match &request.target_id {
+            Some(id) => resource.insert("id".to_string(), id.clone()),
+            None => resource.remove("id"),
+        };
+        // resources whose rows carry attributes policies compare against
+        if matches!(request.resource.as_str(), "user" | "service-account")
+            && let Some(id)=request.target_id.as_ref().and_then(Value::as_i64)
+            && let Ok(target)=self.user_repo.get_by_id(id as i32).await
+        {
+            resource.extend(user_attributes(&target));
+        }
+
+        let mut context=Map::new();
+        context.insert("method".to_string(), request.method.clone().into());
+        context.insert("path".to_string(), request.path.clone().into());
+        if let Some(ip_address)=&request.ip_address {
+            context.insert("ip_address".to_string(), ip_address.clone().into());
+        }
+        Ok(Attributes { subject, resource, context })
+    }
+
+    async fn decide(&self, identity: &UserIdentity, request: &AccessRequest) -> Result<Decision, CommonError> {
+        let grant=scope_key(&request.resource, &request.action);
+        if let Some(scopes)=&identity.scopes && !scopes.contains(&grant) {
+            return Ok(Decision::deny(format!("API key is not scoped for {}", grant)));
+        }
+
+        let permissions=self.load(identity.user_id, identity.permissions_version).await?;
+        let candidates: Vec<&Permission>=permissions
+            .iter()
+            .filter(|permission| permission.allows(&request.resource, &request.action))
+            .collect();
+        if candidates.is_empty() {
+            return Ok(Decision::deny(format!("No role grants {}", grant)));
+        }
+        if let Some(permission)=candidates.iter().find(|permission| permission.policy.is_none()) {
+            return Ok(Decision::allow(format!("{} granted by permission {}", grant, permission.id)));
+        }
+
+        let attributes=self.attributes(identity, request).await?;
+        let mut failures: Vec<String>=Vec::new();
+        for permission in candidates {
+            let Some(policy)=&permission.policy else {
+                continue;
+            };
+            match policy.evaluate(&attributes) {
+                Ok(true) => return Ok(Decision::allow(format!("{} granted by permission {} since {}", grant, permission.id, policy))),
+                Ok(false) => failures.push(format!("permission {} requires {}", permission.id, policy)),
+                Err(missing) => failures.push(format!("permission {} requires {} but {}", permission.id, policy, missing)),
+            }
+        }
+        Ok(Decision::deny(format!("{} denied: {}", grant, failures.join("; "))))
+    }
+}
+
+fn user_attributes(user: &User) -> Map<String, Value> {
+    let mut attributes=Map::new();
+    attributes.insert("id".to_string(), user.id.into());
+    attributes.insert("username".to_string(), user.username.clone().into());
+    attributes.insert("email".to_string(), user.email.clone().into());
+    attributes.insert("department".to_string(), user.department.clone().into());
+    attributes.insert("is_service_account".to_string(), user.is_service_account.into());
+    attributes
+}
+
+#[async_trait]
+impl AuthorizationService for AuthorizationServiceImpl {
+    async fn get_permissions(&self, user_id: i32) -> Result<Vec<Permission>, CommonError>{
+        let permissions=self.load(user_id, None).await?;
+        Ok(permissions.as_ref().clone())
+    }
+    async fn authorize(&self, identity: &UserIdentity, request: AccessRequest) -> Result<Decision, CommonError>{
+        let decision=self.decide(identity, &request).await?;
+        if !decision.allowed || self.config.audit_allowed {
+            let entry=NewAuditLog {
+                user_id: identity.user_id,
+                api_key_id: identity.api_key_id,
+                resource: request.resource,
+                action: request.action.to_string(),
+                resource_id: request.target_id.as_ref().map(|id| id.as_str().map(str::to_string).unwrap_or_else(|| id.to_string())),
+                allowed: decision.allowed,
+                reason: decision.reason.clone(),
+                method: request.method,
+                path: request.path,
+                ip_address: request.ip_address,
+            };
+            // a failing audit write must not take the API down with it
+            if let Err(e)=self.audit_repo.record(entry).await {
+                tracing::error!("Can't write audit log for user {}: {}", identity.user_id, e.message);
+            }
+        }
+        Ok(decision)
+    }
+    async fn get_grants(&self, identity: &UserIdentity) -> Result<Vec<String>, CommonError>{
+        let permissions=self.load(identity.user_id, identity.permissions_version).await?;
+        let mut grants: Vec<String>=Vec::new();
+        for permission in permissions.iter() {
+            for action in &permission.action {
+                let grant=scope_key(&permission.resource, &action.key);
+                let in_scope=identity.scopes.as_ref().is_none_or(|scopes| scopes.contains(&grant));
+                if in_scope && !grants.contains(&grant) {
+                    grants.push(grant);
+                }
+            }
+        }
+        Ok(grants)
+    }
+    async fn invalidate_users(&self, user_ids: Vec<i32>) -> Result<(), CommonError>{
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        {
+            let mut cache = self.cache.write().unwrap_or_else(PoisonError::into_inner);
+            for user_id in &user_ids {
+                cache.remove(user_id);
+            }
+        }
+        // other instances notice through the `pv` claim of tokens issued from now on
+        self.user_repo.bump_permissions_version(user_ids).await.map_err(|e|e.into())
+    }
+    async fn invalidate_roles(&self, role_ids: Vec<i32>) -> Result<(), CommonError>{
+        let roles=self.role_repo.get().await.map_err(|e|e.into())?;
+        let affected: Vec<i32>=roles
+            .iter()
+            .filter(|role| role_chain(&roles, role.id).iter().any(|ancestor| role_ids.contains(&ancestor.id)))
+            .map(|role| role.id)
+            .collect();
+        let user_ids=self.role_repo.get_member_ids(affected).await.map_err(|e|e.into())?;
+        self.invalidate_users(user_ids).await
+    }
+}
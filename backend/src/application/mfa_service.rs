@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rand::Rng;
+use serde::Serialize;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::config::MfaConfig;
+use crate::domain::error::CommonError;
+use crate::domain::role::repo::{Role, RoleRepo};
+use crate::domain::security::mfa::MfaRepo;
+use crate::domain::security::repo::sha256_hex;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[async_trait]
+pub trait MfaService:Sync + Send {
+    async fn is_enabled(&self, user_id: i32) -> Result<bool, CommonError>;
+    // one of the user's roles requires two-factor authentication
+    async fn is_required(&self, user_id: i32) -> Result<bool, CommonError>;
+    // `account_name` is what authenticator apps display next to the issuer
+    async fn enroll(&self, user_id: i32, account_name: &str) -> Result<MfaEnrollment, CommonError>;
+    // checks the first code of an enrollment, enables it and returns the recovery codes
+    async fn confirm(&self, user_id: i32, code: &str) -> Result<Vec<String>, CommonError>;
+    // accepts a current TOTP code or an unused recovery code
+    async fn verify(&self, user_id: i32, code: &str) -> Result<bool, CommonError>;
+    async fn disable(&self, user_id: i32, code: &str) -> Result<(), CommonError>;
+    async fn set_role_requirement(&self, role_id: i32, required: bool) -> Result<Role, CommonError>;
+}
+
+#[derive(Clone)]
+pub struct MfaServiceImpl{
+    pub mfa_repo: Arc<dyn MfaRepo>,
+    pub role_repo: Arc<dyn RoleRepo>,
+    pub config: MfaConfig,
+}
+
+impl MfaServiceImpl {
+    pub fn new(mfa_repo: Arc<dyn MfaRepo>, role_repo: Arc<dyn RoleRepo>, config: MfaConfig)-> Self{
+        Self { mfa_repo, role_repo, config }
+    }
+
+    fn totp(&self, secret: &str, account_name: String) -> Result<TOTP, CommonError> {
+        let bytes = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| CommonError::new(format!("Invalid TOTP secret: {:?}", e), 500))?;
+        TOTP::new(Algorithm::SHA1, TOTP_DIGITS, 0, TOTP_STEP, bytes, Some(self.config.issuer.clone()), account_name)
+            .map_err(|e| CommonError::new(format!("Invalid TOTP secret: {}", e), 500))
+    }
+
+    // the time step `code` belongs to, allowing one step of clock drift either way
+    fn matching_step(&self, totp: &TOTP, code: &str) -> Option<i64> {
+        let now = chrono::Utc::now().timestamp() as u64;
+        let current = now / TOTP_STEP;
+        [current.saturating_sub(1), current, current + 1]
+            .into_iter()
+            .find(|step| totp.check(code, step * TOTP_STEP))
+            .map(|step| step as i64)
+    }
+
+    // TOTP code of the stored secret, each time step is accepted once
+    async fn verify_totp(&self, user_id: i32, secret: &str, code: &str) -> Result<bool, CommonError> {
+        let totp = self.totp(secret, user_id.to_string())?;
+        match self.matching_step(&totp, code.trim()) {
+            Some(step) => self.mfa_repo.use_step(user_id, step).await.map_err(|e| e.into()),
+            None => Ok(false),
+        }
+    }
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::rngs::OsRng;
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = (0..10)
+                .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+// recovery codes are compared without dashes, spaces or case
+fn recovery_code_hash(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    sha256_hex(&normalized)
+}
+
+fn invalid_code() -> CommonError {
+    CommonError::new("Invalid two-factor code", 400)
+}
+
+#[async_trait]
+impl MfaService for MfaServiceImpl {
+    async fn is_enabled(&self, user_id: i32) -> Result<bool, CommonError>{
+        let mfa=self.mfa_repo.get(user_id).await.map_err(|e|e.into())?;
+        Ok(mfa.is_some_and(|mfa| mfa.enabled))
+    }
+    async fn is_required(&self, user_id: i32) -> Result<bool, CommonError>{
+        let roles=self.role_repo.get_roles_by_user_id(user_id).await.map_err(|e|e.into())?;
+        Ok(roles.iter().any(|role| role.require_mfa))
+    }
+    async fn enroll(&self, user_id: i32, account_name: &str) -> Result<MfaEnrollment, CommonError>{
+        if self.is_enabled(user_id).await? {
+            return Err(CommonError::new("Two-factor authentication is already enabled", 409));
+        }
+        let secret=Secret::generate_secret().to_encoded().to_string();
+        let totp=self.totp(&secret, account_name.to_string())?;
+        self.mfa_repo.save_secret(user_id, secret.clone()).await.map_err(|e|e.into())?;
+
+        Ok(MfaEnrollment { secret, otpauth_uri: totp.get_url() })
+    }
+    async fn confirm(&self, user_id: i32, code: &str) -> Result<Vec<String>, CommonError>{
+        let mfa=self.mfa_repo.get(user_id).await.map_err(|e|e.into())?
+            .ok_or_else(|| CommonError::new("Two-factor enrollment was not started", 400))?;
+        if mfa.enabled {
+            return Err(CommonError::new("Two-factor authentication is already enabled", 409));
+        }
+        if !self.verify_totp(user_id, &mfa.secret, code).await? {
+            return Err(invalid_code());
+        }
+
+        let codes=generate_recovery_codes();
+        let hashes=codes.iter().map(|code| recovery_code_hash(code)).collect();
+        self.mfa_repo.enable(user_id, hashes).await.map_err(|e|e.into())?;
+
+        Ok(codes)
+    }
+    async fn verify(&self, user_id: i32, code: &str) -> Result<bool, CommonError>{
+        let Some(mfa)=self.mfa_repo.get(user_id).await.map_err(|e|e.into())? else {
+            return Ok(false);
+        };
+        if !mfa.enabled {
+            return Ok(false);
+        }
+        if self.verify_totp(user_id, &mfa.secret, code).await? {
+            return Ok(true);
+        }
+        self.mfa_repo.use_recovery_code(user_id, recovery_code_hash(code)).await.map_err(|e|e.into())
+    }
+    async fn disable(&self, user_id: i32, code: &str) -> Result<(), CommonError>{
+        if self.is_required(user_id).await? {
+            return Err(CommonError::new("Two-factor authentication is required for your role", 403));
+        }
+        if !self.verify(user_id, code).await? {
+            return Err(invalid_code());
+        }
+        self.mfa_repo.delete(user_id).await.map_err(|e|e.into())
+    }
+    async fn set_role_requirement(&self, role_id: i32, required: bool) -> Result<Role, CommonError>{
+        self.role_repo.get_by_id(role_id).await
+            .map_err(|_| CommonError::new("Role not found", 404))?;
+        self.role_repo.set_require_mfa(role_id, required).await.map_err(|e|e.into())
+    }
+}
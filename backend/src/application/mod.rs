@@ -1,2 +1,10 @@
 pub mod user_service;
-pub mod auth_service;   
\ No newline at end of file
+pub mod auth_service;
+pub mod session_service;
+pub mod mfa_service;
+pub mod account_service;
+pub mod api_key_service;
+pub mod authorization_service;
+pub mod role_service;
+pub mod permission_service;
+pub mod profile_service;
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use common_model::page::{PageData, PageRequest};
+
+use crate::application::authorization_service::AuthorizationService;
+use crate::domain::cursor::check_page_request;
+use crate::domain::error::{CommonError, FieldError};
+use crate::domain::permission::policy::Policy;
+use crate::domain::permission::repo::{is_action_key, Action, ActionRepo, Permission, PermissionRepo};
+
+#[async_trait]
+pub trait PermissionService:Sync + Send {
+    async fn list(&self, page: PageRequest) -> Result<PageData<Permission>, CommonError>;
+    async fn get(&self, id: i32) -> Result<Permission, CommonError>;
+    // `actions` are keys of the `actions` table, unknown keys are rejected
+    // `policy` narrows the grant to the requests it holds for, see domain::permission::policy
+    async fn create(&self, resource: String, actions: Vec<String>, description: Option<String>, policy: Option<Policy>) -> Result<Permission, CommonError>;
+    // a missing `policy` makes the grant unconditional again
+    async fn update(&self, id: i32, resource: String, actions: Vec<String>, description: Option<String>, policy: Option<Policy>) -> Result<Permission, CommonError>;
+    async fn delete(&self, id: i32) -> Result<(), CommonError>;
+
+    async fn list_actions(&self, page: PageRequest) -> Result<PageData<Action>, CommonError>;
+    // adds a key such as EXPORT to the action vocabulary
+    async fn create_action(&self, key: String, description: Option<String>) -> Result<Action, CommonError>;
+    async fn update_action(&self, id: i32, description: String) -> Result<Action, CommonError>;
+    // built-in actions and actions still granted by a permission can't be deleted
+    async fn delete_action(&self, id: i32) -> Result<(), CommonError>;
+}
+
+#[derive(Clone)]
+pub struct PermissionServiceImpl{
+    pub permission_repo: Arc<dyn PermissionRepo>,
+    pub action_repo: Arc<dyn ActionRepo>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
+}
+
+impl PermissionServiceImpl {
+    pub fn new(permission_repo: Arc<dyn PermissionRepo>, action_repo: Arc<dyn ActionRepo>, authorization_service: Arc<dyn AuthorizationService>)-> Self{
+        Self { permission_repo, action_repo, authorization_service }
+    }
+
+    // trimmed resource and the ids of the requested action keys
+    async fn check_permission(&self, resource: &str, keys: &[String], policy: Option<&Policy>) -> Result<(String, Vec<i32>), CommonError> {
+        let mut errors=Vec::new();
+        let resource=resource.trim().to_string();
+        if resource.is_empty() || resource.chars().count() > 50 {
+            errors.push(FieldError::new("resource", "invalid_length", "Resource must be between 1 and 50 characters long"));
+        }
+        if keys.is_empty() {
+            errors.push(FieldError::new("actions", "required", "At least one action is required"));
+        }
+
+        let known=self.action_repo.get().await.map_err(|e|e.into())?;
+        let mut action_ids=Vec::new();
+        let mut unknown=Vec::new();
+        for key in keys {
+            match known.iter().find(|action| &action.key.to_string() == key) {
+                Some(action) if !action_ids.contains(&action.id) => action_ids.push(action.id),
+                Some(_) => {},
+                None => unknown.push(key.clone()),
+            }
+        }
+        if !unknown.is_empty() {
+            errors.push(FieldError::new("actions", "unknown_action", format!("Unknown actions: {}", unknown.join(", "))));
+        }
+        if let Some(Err(message))=policy.map(Policy::check) {
+            errors.push(FieldError::new("policy", "invalid_policy", message));
+        }
+
+        if !errors.is_empty() {
+            return Err(CommonError::validation(errors));
+        }
+        Ok((resource, action_ids))
+    }
+
+    async fn get_action(&self, id: i32) -> Result<Action, CommonError> {
+        self.action_repo.get_by_id(id).await
+            .map_err(|_| CommonError::new("Action not found", 404))
+    }
+}
+
+#[async_trait]
+impl PermissionService for PermissionServiceImpl {
+    async fn list(&self, page: PageRequest) -> Result<PageData<Permission>, CommonError>{
+        check_page_request(&page, "id")?;
+        self.permission_repo.get_page(page).await.map_err(|e|e.into())
+    }
+    async fn get(&self, id: i32) -> Result<Permission, CommonError>{
+        self.permission_repo.get_by_id(id).await
+            .map_err(|_| CommonError::new("Permission not found", 404))
+    }
+    async fn create(&self, resource: String, actions: Vec<String>, description: Option<String>, policy: Option<Policy>) -> Result<Permission, CommonError>{
+        let (resource, action_ids)=self.check_permission(&resource, &actions, policy.as_ref()).await?;
+        self.permission_repo.create(resource, description.unwrap_or_default(), action_ids, policy).await.map_err(|e|e.into())
+    }
+    async fn update(&self, id: i32, resource: String, actions: Vec<String>, description: Option<String>, policy: Option<Policy>) -> Result<Permission, CommonError>{
+        let permission=self.get(id).await?;
+        let (resource, action_ids)=self.check_permission(&resource, &actions, policy.as_ref()).await?;
+        let updated=self.permission_repo.update(permission.id, resource, description.unwrap_or(permission.description), action_ids, policy).await.map_err(|e|e.into())?;
+        let role_ids=self.permission_repo.get_role_ids(permission.id).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_roles(role_ids).await?;
+        Ok(updated)
+    }
+    async fn delete(&self, id: i32) -> Result<(), CommonError>{
+        let permission=self.get(id).await?;
+        // the roles have to be looked up before the grants cascade away
+        let role_ids=self.permission_repo.get_role_ids(permission.id).await.map_err(|e|e.into())?;
+        self.permission_repo.delete_by_id(permission.id).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_roles(role_ids).await?;
+        Ok(())
+    }
+
+    async fn list_actions(&self, page: PageRequest) -> Result<PageData<Action>, CommonError>{
+        check_page_request(&page, "id")?;
+        self.action_repo.get_page(page).await.map_err(|e|e.into())
+    }
+    async fn create_action(&self, key: String, description: Option<String>) -> Result<Action, CommonError>{
+        let key=key.trim().to_string();
+        if !is_action_key(&key) {
+            return Err(CommonError::validation(vec![FieldError::new("key", "invalid_format", "Key must be upper case letters, digits or underscores, starting with a letter, at most 50 characters")]));
+        }
+        if self.action_repo.get_by_key(key.clone()).await.map_err(|e|e.into())?.is_some() {
+            return Err(CommonError::new("An action with this key already exists", 409));
+        }
+        self.action_repo.create(key, description.unwrap_or_default()).await.map_err(|e|e.into())
+    }
+    async fn update_action(&self, id: i32, description: String) -> Result<Action, CommonError>{
+        let action=self.get_action(id).await?;
+        self.action_repo.update(action.id, description).await.map_err(|e|e.into())
+    }
+    async fn delete_action(&self, id: i32) -> Result<(), CommonError>{
+        let action=self.get_action(id).await?;
+        if action.key.is_builtin() {
+            return Err(CommonError::new("Built-in actions can't be deleted", 409));
+        }
+        let usage=self.action_repo.count_usage(action.id).await.map_err(|e|e.into())?;
+        if usage > 0 {
+            return Err(CommonError::new(format!("Action is still granted by {} permission(s), remove it from them first", usage), 409));
+        }
+        self.action_repo.delete_by_id(action.id).await.map_err(|e|e.into())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::application::authorization_service::AuthorizationService;
+use crate::application::mfa_service::MfaService;
+use crate::domain::error::CommonError;
+use crate::domain::menu::repo::{MenuItem, MenuLabel, MenuRepo};
+use crate::domain::permission::repo::scope_key;
+use crate::domain::role::repo::{RoleGrant, RoleRepo};
+use crate::domain::user::repo::{UserIdentity, UserRepo};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileUser {
+    pub id: i32,
+    pub username: String,
+    pub email: String,
+    pub department: Option<String>,
+    pub email_verified: bool,
+    pub mfa_enabled: bool,
+    pub is_service_account: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub user: ProfileUser,
+    pub roles: Vec<RoleGrant>,
+    // "resource:ACTION", inherited ones included
+    pub permissions: Vec<String>,
+}
+
+// shaped like the frontend's MenuItem
+#[derive(Debug, Clone, Serialize)]
+pub struct MenuNode {
+    pub code: String,
+    pub label: MenuLabel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    pub path: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<MenuNode>,
+}
+
+#[async_trait]
+pub trait ProfileService:Sync + Send {
+    async fn get_profile(&self, identity: &UserIdentity) -> Result<Profile, CommonError>;
+    // the menu tree without the items the caller isn't allowed to see
+    async fn get_menu(&self, identity: &UserIdentity) -> Result<Vec<MenuNode>, CommonError>;
+}
+
+#[derive(Clone)]
+pub struct ProfileServiceImpl{
+    pub user_repo: Arc<dyn UserRepo>,
+    pub role_repo: Arc<dyn RoleRepo>,
+    pub menu_repo: Arc<dyn MenuRepo>,
+    pub mfa_service: Arc<dyn MfaService>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
+}
+
+impl ProfileServiceImpl {
+    pub fn new(user_repo: Arc<dyn UserRepo>, role_repo: Arc<dyn RoleRepo>, menu_repo: Arc<dyn MenuRepo>, mfa_service: Arc<dyn MfaService>, authorization_service: Arc<dyn AuthorizationService>)-> Self{
+        Self { user_repo, role_repo, menu_repo, mfa_service, authorization_service }
+    }
+}
+
+fn is_visible(item: &MenuItem, grants: &[String]) -> bool {
+    match (&item.resource, &item.action) {
+        (Some(resource), Some(action)) => grants.contains(&scope_key(resource, action)),
+        (None, None) => true,
+        // half a requirement is a misconfiguration, hide the item rather than guess
+        _ => false,
+    }
+}
+
+// visible children of `parent_id`. A group whose children are all hidden is dropped as well
+fn build_menu(items: &[MenuItem], parent_id: Option<i32>, grants: &[String]) -> Vec<MenuNode> {
+    items
+        .iter()
+        .filter(|item| item.parent_id == parent_id && is_visible(item, grants))
+        .filter_map(|item| {
+            let has_children = items.iter().any(|child| child.parent_id == Some(item.id));
+            let children = build_menu(items, Some(item.id), grants);
+            if has_children && children.is_empty() {
+                return None;
+            }
+            Some(MenuNode {
+                code: item.code.clone(),
+                label: item.label.clone(),
+                icon: item.icon.clone(),
+                path: item.path.clone(),
+                children,
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl ProfileService for ProfileServiceImpl {
+    async fn get_profile(&self, identity: &UserIdentity) -> Result<Profile, CommonError>{
+        let user=self.user_repo.get_by_id(identity.user_id).await
+            .map_err(|_| CommonError::new("User not found", 404))?;
+        let mfa_enabled=self.mfa_service.is_enabled(user.id).await?;
+        let roles=self.role_repo.get_grants_by_user_id(user.id).await.map_err(|e|e.into())?;
+        let permissions=self.authorization_service.get_grants(identity).await?;
+
+        Ok(Profile {
+            user: ProfileUser {
+                id: user.id,
+                username: user.username,
+                email: user.email,
+                department: user.department,
+                email_verified: user.email_verified,
+                mfa_enabled,
+                is_service_account: user.is_service_account,
+            },
+            roles,
+            permissions,
+        })
+    }
+    async fn get_menu(&self, identity: &UserIdentity) -> Result<Vec<MenuNode>, CommonError>{
+        let items=self.menu_repo.get().await.map_err(|e|e.into())?;
+        let grants=self.authorization_service.get_grants(identity).await?;
+        Ok(build_menu(&items, None, &grants))
+    }
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::Serialize;
+
+use crate::application::authorization_service::AuthorizationService;
+use crate::domain::cursor::check_page_request;
+use crate::domain::error::{CommonError, FieldError};
+use crate::domain::permission::repo::{Permission, PermissionRepo};
+use crate::domain::role::repo::{role_chain, Role, RoleDelete, RoleGrant, RoleMember, RoleRepo};
+use crate::domain::user::repo::UserRepo;
+
+// a permission a role holds, directly or through an ancestor
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivePermission {
+    #[serde(flatten)]
+    pub permission: Permission,
+    // the nearest role in the chain that was granted the permission
+    pub source_role_id: i32,
+    pub source_role_name: String,
+    pub inherited: bool,
+}
+
+#[async_trait]
+pub trait RoleService:Sync + Send {
+    async fn list(&self, page: PageRequest) -> Result<PageData<Role>, CommonError>;
+    async fn get(&self, id: i32) -> Result<Role, CommonError>;
+    async fn create(&self, name: String, description: Option<String>, parent_role_id: Option<i32>) -> Result<Role, CommonError>;
+    // a parent that is the role itself or one of its descendants is rejected
+    async fn update(&self, id: i32, name: String, description: Option<String>, parent_role_id: Option<i32>) -> Result<Role, CommonError>;
+    // refused while users still hold the role or other roles inherit from it
+    async fn delete(&self, id: i32) -> Result<(), CommonError>;
+    async fn get_permissions(&self, id: i32) -> Result<Vec<Permission>, CommonError>;
+    // union of the permissions of the role and its ancestors
+    async fn get_effective_permissions(&self, id: i32) -> Result<Vec<EffectivePermission>, CommonError>;
+    async fn add_permission(&self, id: i32, permission_id: i32) -> Result<Vec<Permission>, CommonError>;
+    async fn remove_permission(&self, id: i32, permission_id: i32) -> Result<(), CommonError>;
+
+    async fn get_user_roles(&self, user_id: i32, page: PageRequest) -> Result<PageData<RoleGrant>, CommonError>;
+    async fn get_members(&self, id: i32, page: PageRequest) -> Result<PageData<RoleMember>, CommonError>;
+    // `expires_at` makes the grant temporary, e.g. elevated access during an incident.
+    // Returns the first page of the user's grants
+    async fn grant(&self, user_id: i32, id: i32, expires_at: Option<NaiveDateTime>) -> Result<PageData<RoleGrant>, CommonError>;
+    // all or nothing, unknown users fail the whole request. Returns the first page of the members
+    async fn grant_to_users(&self, user_ids: Vec<i32>, id: i32, expires_at: Option<NaiveDateTime>) -> Result<PageData<RoleMember>, CommonError>;
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<(), CommonError>;
+    // grants the admin role to the user named by username or email, but only while no user holds
+    // it. Returns whether the role was granted
+    async fn bootstrap_admin(&self, email_or_username: &str) -> Result<bool, CommonError>;
+}
+
+// seeded by the admin_permissions migration
+const ADMIN_ROLE: &str = "admin";
+
+#[derive(Clone)]
+pub struct RoleServiceImpl{
+    pub role_repo: Arc<dyn RoleRepo>,
+    pub permission_repo: Arc<dyn PermissionRepo>,
+    pub user_repo: Arc<dyn UserRepo>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
+}
+
+impl RoleServiceImpl {
+    pub fn new(role_repo: Arc<dyn RoleRepo>, permission_repo: Arc<dyn PermissionRepo>, user_repo: Arc<dyn UserRepo>, authorization_service: Arc<dyn AuthorizationService>)-> Self{
+        Self { role_repo, permission_repo, user_repo, authorization_service }
+    }
+
+    async fn check_user(&self, user_id: i32) -> Result<i32, CommonError> {
+        let user=self.user_repo.get_by_id(user_id).await
+            .map_err(|_| CommonError::new("User not found", 404))?;
+        Ok(user.id)
+    }
+
+    // trimmed name, unique among the other roles
+    async fn check_name(&self, name: &str, id: Option<i32>) -> Result<String, CommonError> {
+        let name=name.trim().to_string();
+        if name.is_empty() || name.chars().count() > 50 {
+            return Err(CommonError::validation(vec![FieldError::new("name", "invalid_length", "Name must be between 1 and 50 characters long")]));
+        }
+        let existing=self.role_repo.get_by_name(name.clone()).await.map_err(|e|e.into())?;
+        if existing.is_some_and(|role| Some(role.id) != id) {
+            return Err(CommonError::new("A role with this name already exists", 409));
+        }
+        Ok(name)
+    }
+
+    // the parent must exist and must not have `id` among its ancestors
+    async fn check_parent(&self, parent_role_id: Option<i32>, id: Option<i32>) -> Result<(), CommonError> {
+        let Some(parent_role_id)=parent_role_id else {
+            return Ok(());
+        };
+        let roles=self.role_repo.get().await.map_err(|e|e.into())?;
+        if !roles.iter().any(|role| role.id == parent_role_id) {
+            return Err(CommonError::validation(vec![FieldError::new("parent_role_id", "unknown_role", "Parent role not found")]));
+        }
+        if let Some(id)=id
+            && role_chain(&roles, parent_role_id).iter().any(|role| role.id == id)
+        {
+            return Err(CommonError::validation(vec![FieldError::new("parent_role_id", "cycle", "A role can't inherit from itself or one of its descendants")]));
+        }
+        Ok(())
+    }
+}
+
+fn check_expiry(expires_at: Option<NaiveDateTime>) -> Result<(), CommonError> {
+    if expires_at.is_some_and(|expires_at| expires_at <= chrono::Utc::now().naive_utc()) {
+        return Err(CommonError::validation(vec![FieldError::new("expires_at", "in_past", "Expiry must be in the future")]));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl RoleService for RoleServiceImpl {
+    async fn list(&self, page: PageRequest) -> Result<PageData<Role>, CommonError>{
+        check_page_request(&page, "id")?;
+        self.role_repo.get_page(page).await.map_err(|e|e.into())
+    }
+    async fn get(&self, id: i32) -> Result<Role, CommonError>{
+        self.role_repo.get_by_id(id).await
+            .map_err(|_| CommonError::new("Role not found", 404))
+    }
+    async fn create(&self, name: String, description: Option<String>, parent_role_id: Option<i32>) -> Result<Role, CommonError>{
+        let name=self.check_name(&name, None).await?;
+        self.check_parent(parent_role_id, None).await?;
+        self.role_repo.create(name, description.unwrap_or_default(), parent_role_id).await.map_err(|e|e.into())
+    }
+    async fn update(&self, id: i32, name: String, description: Option<String>, parent_role_id: Option<i32>) -> Result<Role, CommonError>{
+        let role=self.get(id).await?;
+        let name=self.check_name(&name, Some(role.id)).await?;
+        self.check_parent(parent_role_id, Some(role.id)).await?;
+        let updated=self.role_repo.update(role.id, name, description.unwrap_or(role.description), parent_role_id).await.map_err(|e|e.into())?;
+        // a new parent changes what the members inherit
+        if updated.parent_role_id != role.parent_role_id {
+            self.authorization_service.invalidate_roles(vec![role.id]).await?;
+        }
+        Ok(updated)
+    }
+    async fn delete(&self, id: i32) -> Result<(), CommonError>{
+        match self.role_repo.delete_by_id(id).await.map_err(|e|e.into())? {
+            RoleDelete::Deleted => Ok(()),
+            RoleDelete::NotFound => Err(CommonError::new("Role not found", 404)),
+            RoleDelete::Assigned(members) => Err(CommonError::new(format!("Role is still assigned to {} user(s), remove it from them first", members), 409)),
+            RoleDelete::Inherited(children) => Err(CommonError::new(format!("{} role(s) inherit from this role, change their parent first", children), 409)),
+        }
+    }
+    async fn get_permissions(&self, id: i32) -> Result<Vec<Permission>, CommonError>{
+        let role=self.get(id).await?;
+        self.permission_repo.get_permissions_by_role_id(role.id).await.map_err(|e|e.into())
+    }
+    async fn get_effective_permissions(&self, id: i32) -> Result<Vec<EffectivePermission>, CommonError>{
+        let role=self.get(id).await?;
+        let roles=self.role_repo.get().await.map_err(|e|e.into())?;
+
+        let mut effective: Vec<EffectivePermission>=Vec::new();
+        for source in role_chain(&roles, role.id) {
+            let granted=self.permission_repo.get_permissions_by_role_id(source.id).await.map_err(|e|e.into())?;
+            for permission in granted {
+                if effective.iter().any(|e| e.permission.id == permission.id) {
+                    continue;
+                }
+                effective.push(EffectivePermission {
+                    permission,
+                    source_role_id: source.id,
+                    source_role_name: source.name.clone(),
+                    inherited: source.id != role.id,
+                });
+            }
+        }
+        Ok(effective)
+    }
+    async fn add_permission(&self, id: i32, permission_id: i32) -> Result<Vec<Permission>, CommonError>{
+        let role=self.get(id).await?;
+        self.permission_repo.get_by_id(permission_id).await
+            .map_err(|_| CommonError::new("Permission not found", 404))?;
+        self.role_repo.add_permission(role.id, permission_id).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_roles(vec![role.id]).await?;
+        self.permission_repo.get_permissions_by_role_id(role.id).await.map_err(|e|e.into())
+    }
+    async fn remove_permission(&self, id: i32, permission_id: i32) -> Result<(), CommonError>{
+        let role=self.get(id).await?;
+        let removed=self.role_repo.remove_permission(role.id, permission_id).await.map_err(|e|e.into())?;
+        if removed == 0 {
+            return Err(CommonError::new("Permission is not assigned to this role", 404));
+        }
+        self.authorization_service.invalidate_roles(vec![role.id]).await?;
+        Ok(())
+    }
+
+    async fn get_user_roles(&self, user_id: i32, page: PageRequest) -> Result<PageData<RoleGrant>, CommonError>{
+        check_page_request(&page, "id")?;
+        let user_id=self.check_user(user_id).await?;
+        self.role_repo.get_grants_page(user_id, page).await.map_err(|e|e.into())
+    }
+    async fn get_members(&self, id: i32, page: PageRequest) -> Result<PageData<RoleMember>, CommonError>{
+        check_page_request(&page, "username")?;
+        let role=self.get(id).await?;
+        self.role_repo.get_members(role.id, page).await.map_err(|e|e.into())
+    }
+    async fn grant(&self, user_id: i32, id: i32, expires_at: Option<NaiveDateTime>) -> Result<PageData<RoleGrant>, CommonError>{
+        check_expiry(expires_at)?;
+        let user_id=self.check_user(user_id).await?;
+        let role=self.get(id).await?;
+        self.role_repo.grant(user_id, role.id, expires_at).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_users(vec![user_id]).await?;
+        self.role_repo.get_grants_page(user_id, PageRequest::default()).await.map_err(|e|e.into())
+    }
+    async fn grant_to_users(&self, user_ids: Vec<i32>, id: i32, expires_at: Option<NaiveDateTime>) -> Result<PageData<RoleMember>, CommonError>{
+        check_expiry(expires_at)?;
+        if user_ids.is_empty() {
+            return Err(CommonError::validation(vec![FieldError::new("user_ids", "required", "At least one user is required")]));
+        }
+        let role=self.get(id).await?;
+        let existing=self.user_repo.get_existing_ids(user_ids.clone()).await.map_err(|e|e.into())?;
+        let unknown: Vec<String>=user_ids.iter()
+            .filter(|user_id| !existing.contains(user_id))
+            .map(|user_id| user_id.to_string())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(CommonError::validation(vec![FieldError::new("user_ids", "unknown_user", format!("Unknown users: {}", unknown.join(", ")))]));
+        }
+
+        self.role_repo.grant_to_users(existing.clone(), role.id, expires_at).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_users(existing).await?;
+        self.role_repo.get_members(role.id, PageRequest::default()).await.map_err(|e|e.into())
+    }
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<(), CommonError>{
+        let revoked=self.role_repo.revoke(user_id, id).await.map_err(|e|e.into())?;
+        if revoked == 0 {
+            return Err(CommonError::new("Role is not granted to this user", 404));
+        }
+        self.authorization_service.invalidate_users(vec![user_id]).await?;
+        Ok(())
+    }
+    async fn bootstrap_admin(&self, email_or_username: &str) -> Result<bool, CommonError>{
+        let role=self.role_repo.get_by_name(ADMIN_ROLE.to_string()).await.map_err(|e|e.into())?
+            .ok_or_else(|| CommonError::new("The admin role is missing, run the migrations", 500))?;
+        let members=self.role_repo.get_members(role.id, PageRequest { page_size: Some(1), ..Default::default() }).await.map_err(|e|e.into())?;
+        if members.total > 0 {
+            return Ok(false);
+        }
+        let user=self.user_repo.get_by_email_or_username(email_or_username.to_string()).await
+            .map_err(|_| CommonError::new(format!("User {} not found", email_or_username), 404))?;
+        self.role_repo.grant(user.id, role.id, None).await.map_err(|e|e.into())?;
+        self.authorization_service.invalidate_users(vec![user.id]).await?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use common_model::page::{PageData, PageRequest};
+
+use crate::domain::cursor::check_page_request;
+use crate::domain::error::CommonError;
+use crate::domain::security::token::{Session, TokenRepo};
+
+
+
+#[async_trait]
+pub trait SessionService:Sync + Send {
+    // `current` marks the session of the caller, if it belongs to `user_id`
+    async fn list(&self, user_id: i32, current: Option<&str>, page: PageRequest) -> Result<PageData<Session>, CommonError>;
+    async fn revoke(&self, user_id: i32, session_id: String) -> Result<(), CommonError>;
+    async fn revoke_others(&self, user_id: i32, keep_session_id: String) -> Result<usize, CommonError>;
+    async fn revoke_all(&self, user_id: i32) -> Result<usize, CommonError>;
+}
+
+#[derive(Clone)]
+pub struct SessionServiceImpl{
+    pub token_repo: Arc<dyn TokenRepo>,
+}
+
+impl SessionServiceImpl {
+    pub fn new(token_repo: Arc<dyn TokenRepo>)-> Self{
+        Self { token_repo }
+    }
+}
+
+#[async_trait]
+impl SessionService for SessionServiceImpl {
+    async fn list(&self, user_id: i32, current: Option<&str>, page: PageRequest) -> Result<PageData<Session>, CommonError>{
+        check_page_request(&page, "-id")?;
+        let mut sessions=self.token_repo.get_sessions_by_user_id(user_id, page).await.map_err(|e|e.into())?;
+        for session in sessions.data.iter_mut(){
+            session.current = current == Some(session.id.as_str());
+        }
+        Ok(sessions)
+    }
+    async fn revoke(&self, user_id: i32, session_id: String) -> Result<(), CommonError>{
+        let revoked=self.token_repo.revoke_session(user_id, session_id).await.map_err(|e|e.into())?;
+        if revoked == 0 {
+            return Err(CommonError::new("Session not found", 404));
+        }
+        Ok(())
+    }
+    async fn revoke_others(&self, user_id: i32, keep_session_id: String) -> Result<usize, CommonError>{
+        // an empty id keeps nothing and would end every session
+        if keep_session_id.is_empty() {
+            return Err(CommonError::new("There is no current session to keep", 400));
+        }
+        self.token_repo.revoke_other_sessions(user_id, keep_session_id).await.map_err(|e|e.into())
+    }
+    async fn revoke_all(&self, user_id: i32) -> Result<usize, CommonError>{
+        self.token_repo.revoke_by_user_id(user_id).await.map_err(|e|e.into())
+    }
+}
@@ -1,30 +1,508 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use common_model::page::PageData;
+use common_model::user::FilterUserRequest;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use serde::Serialize;
 
-use crate::domain::{permission::repo::PermissionRepo, role::repo::RoleRepo, user::repo::UserRepo};
+use crate::application::account_service::AccountService;
+use crate::application::authorization_service::AuthorizationService;
+use crate::domain::sheet::{csv_rows, text_cell, Sheet, SheetFormat};
+use crate::domain::{cursor::{check_page, MAX_PAGE_SIZE}, error::{CommonError, FieldError}, permission::repo::{scope_key, Type}, role::repo::RoleRepo, security::{repo::SecurityService, token::TokenRepo}, user::repo::{parse_user_sort, user_sort_key, NewAccount, User, UserCreate, UserIdentity, UserPatch, UserRepo, UserUpdate}};
 
+// rows one import may hold
+pub const MAX_IMPORT_ROWS: usize = 1000;
 
+// columns of an export, an import reads the ones it knows and ignores the rest
+const EXPORT_COLUMNS: [&str; 9] = ["id", "username", "email", "employee_id", "department", "is_active", "email_verified", "created_at", "updated_at"];
+
+// what is wrong with one row of an import
+#[derive(Debug, Clone, Serialize)]
+pub struct RowError {
+    // as numbered in the spreadsheet, the header is row 1
+    pub row: usize,
+    #[serde(flatten)]
+    pub error: FieldError,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    // non-blank rows in the file
+    pub rows: usize,
+    // empty on a dry run or when any row has errors, nothing is created then
+    pub created: Vec<User>,
+    pub errors: Vec<RowError>,
+}
 
 #[async_trait]
 pub trait UserService:Sync + Send {
-    
+    // one page of the users matching `filter`
+    async fn list(&self, filter: FilterUserRequest) -> Result<PageData<User>, CommonError>;
+    async fn get(&self, id: i32) -> Result<User, CommonError>;
+    // account made by an administrator. Granting `role_id` needs role:UPDATE as well
+    async fn create(&self, actor: &UserIdentity, username: String, email: String, password: String, department: Option<String>, role_id: Option<i32>) -> Result<User, CommonError>;
+    // applies the Some fields of `patch`. `version` is the one the caller read, a user changed
+    // since then fails with 412 instead of being overwritten. A new email has to be verified again
+    async fn update(&self, actor: &UserIdentity, id: i32, patch: UserPatch, version: Option<i32>) -> Result<User, CommonError>;
+    // blocks login and ends every session, nothing is deleted
+    async fn deactivate(&self, actor: &UserIdentity, id: i32) -> Result<User, CommonError>;
+    // all or nothing, unknown users fail the whole request
+    async fn deactivate_many(&self, actor: &UserIdentity, ids: Vec<i32>) -> Result<Vec<i32>, CommonError>;
+    async fn reactivate(&self, id: i32) -> Result<User, CommonError>;
+    // lifts a brute-force lockout before it runs out
+    async fn unlock(&self, user_id: i32) -> Result<User, CommonError>;
+    // creates an account per row of the file, all of them or none. Rows without a password get
+    // a link to choose one. `dry_run` only checks the rows
+    async fn import(&self, actor: &UserIdentity, format: SheetFormat, file: Vec<u8>, dry_run: bool) -> Result<ImportReport, CommonError>;
+    // the users matching `filter` as a file. CSV is sent a page at a time, a workbook once it is complete
+    async fn export(&self, filter: FilterUserRequest, format: SheetFormat) -> Result<BoxStream<'static, Result<Vec<u8>, CommonError>>, CommonError>;
 }
 
 #[derive(Clone)]
 pub struct UserServiceImpl{
     pub user_repo: Arc<dyn UserRepo>,
     pub role_repo: Arc<dyn RoleRepo>,
-    pub permission_repo: Arc<dyn PermissionRepo>,
+    pub token_repo: Arc<dyn TokenRepo>,
+    pub security_service: Arc<dyn SecurityService>,
+    pub account_service: Arc<dyn AccountService>,
+    pub authorization_service: Arc<dyn AuthorizationService>,
 }
 
 impl UserServiceImpl {
-    pub fn new(user_repo: Arc<dyn UserRepo>,role_repo: Arc<dyn RoleRepo>,permission_repo: Arc<dyn PermissionRepo>)-> Self{
-        Self { user_repo, role_repo , permission_repo }
+    pub fn new(user_repo: Arc<dyn UserRepo>, role_repo: Arc<dyn RoleRepo>, token_repo: Arc<dyn TokenRepo>, security_service: Arc<dyn SecurityService>, account_service: Arc<dyn AccountService>, authorization_service: Arc<dyn AuthorizationService>)-> Self{
+        Self { user_repo, role_repo, token_repo, security_service, account_service, authorization_service }
+    }
+
+    // trimmed username and email, neither taken by another user
+    async fn check_account(&self, username: &str, email: &str, id: Option<i32>) -> Result<(String, String), CommonError> {
+        let patch=UserPatch { username: Some(username.to_string()), email: Some(email.to_string()), ..Default::default() };
+        let patch=self.check_patch(patch, id).await?;
+        Ok((patch.username.unwrap_or_default(), patch.email.unwrap_or_default()))
+    }
+
+    // `patch` with its values trimmed, 400 when one is malformed and 409 when the username or
+    // email belongs to another user than `id`
+    async fn check_patch(&self, patch: UserPatch, id: Option<i32>) -> Result<UserPatch, CommonError> {
+        let patch=check_fields(patch).map_err(CommonError::validation)?;
+        if let Some(field)=self.find_taken(&patch, id).await {
+            return Err(taken(field));
+        }
+        Ok(patch)
+    }
+
+    // "username" or "email" when the value in `patch` belongs to another user than `id`
+    async fn find_taken(&self, patch: &UserPatch, id: Option<i32>) -> Option<&'static str> {
+        for (value, field) in [(&patch.username, "username"), (&patch.email, "email")] {
+            if let Some(value)=value
+                && let Ok(existing)=self.user_repo.get_by_email_or_username(value.clone()).await
+                && Some(existing.id) != id
+            {
+                return Some(field);
+            }
+        }
+        None
+    }
+
+    async fn revoke_sessions(&self, user_id: i32) -> Result<(), CommonError> {
+        self.token_repo.revoke_by_user_id(user_id).await.map_err(|e|e.into())?;
+        Ok(())
+    }
+}
+
+fn check_filter(filter: &FilterUserRequest) -> Result<(), CommonError> {
+    let mut errors=Vec::new();
+    match parse_user_sort(filter.sort.as_deref().unwrap_or_default()) {
+        Err(message) => errors.push(FieldError::new("sort", "invalid_sort", message)),
+        Ok(sort) if filter.cursor.as_deref().is_some_and(|cursor| !cursor.trim().is_empty()) && sort.iter().any(|key| key.field.nullable()) => {
+            errors.push(FieldError::new("cursor", "invalid_cursor", "Lists sorted by employeeId, isActive or createdAt are only paged by number"));
+        }
+        Ok(sort) => errors.extend(check_page(filter.page_num, filter.page_size, filter.cursor.as_deref(), &user_sort_key(&sort))),
+    }
+    if let (Some(from), Some(to))=(filter.created_from, filter.created_to) && from > to {
+        errors.push(FieldError::new("createdFrom", "invalid_range", "createdFrom must not be after createdTo"));
+    }
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(CommonError::validation(errors))
+}
+
+fn file_error(code: &str, message: impl Into<String>) -> CommonError {
+    CommonError::validation(vec![FieldError::new("file", code, message)])
+}
+
+fn export_row(user: &User) -> Vec<String> {
+    vec![
+        user.id.to_string(),
+        text_cell(&user.username),
+        text_cell(&user.email),
+        // 0 stands for none, see UserDiesel
+        if user.employee_id == 0 { String::new() } else { user.employee_id.to_string() },
+        user.department.as_deref().map(text_cell).unwrap_or_default(),
+        user.is_active.to_string(),
+        user.email_verified.to_string(),
+        user.created_at.to_string(),
+        user.updated_at.map(|updated_at| updated_at.to_string()).unwrap_or_default(),
+    ]
+}
+
+// every page of the users matching `filter`, following next_cursor where the sort has cursors
+// and page numbers where it hasn't
+fn export_pages(user_repo: Arc<dyn UserRepo>, mut filter: FilterUserRequest) -> impl Stream<Item = Result<Vec<User>, CommonError>> {
+    filter.page_num=Some(1);
+    filter.page_size=Some(MAX_PAGE_SIZE);
+    filter.cursor=None;
+    stream::unfold(Some(filter), move |filter| {
+        let user_repo=user_repo.clone();
+        async move {
+            let mut filter=filter?;
+            let page=match user_repo.get(filter.clone()).await {
+                Ok(page) => page,
+                Err(e) => return Some((Err(e.into()), None)),
+            };
+            let next=match page.next_cursor {
+                Some(cursor) => {
+                    filter.cursor=Some(cursor);
+                    Some(filter)
+                }
+                None if filter.cursor.is_none() && page.page_num * page.page_size < page.total => {
+                    filter.page_num=Some(page.page_num + 1);
+                    Some(filter)
+                }
+                None => None,
+            };
+            Some((Ok(page.data), next))
+        }
+    })
+}
+
+// `patch` with its values trimmed, Err lists the malformed ones
+fn check_fields(mut patch: UserPatch) -> Result<UserPatch, Vec<FieldError>> {
+    let mut errors=Vec::new();
+    patch.username=patch.username.map(|username| username.trim().to_string());
+    patch.email=patch.email.map(|email| email.trim().to_string());
+    if let Some(username)=&patch.username && (username.is_empty() || username.chars().count() > 50) {
+        errors.push(FieldError::new("username", "invalid_length", "Username must be between 1 and 50 characters long"));
+    }
+    if let Some(email)=&patch.email
+        && (email.chars().count() > 255 || !email.split_once('@').is_some_and(|(local, domain)| !local.is_empty() && !domain.is_empty()))
+    {
+        errors.push(FieldError::new("email", "invalid_format", "Email must be a valid address"));
+    }
+    if let Some(Some(employee_id))=patch.employee_id && employee_id < 1 {
+        errors.push(FieldError::new("employee_id", "out_of_range", "Employee id must be positive"));
+    }
+    if let Some(department)=patch.department.take() {
+        match check_department(department) {
+            Ok(department) => patch.department=Some(department),
+            Err(e) => errors.extend(e.errors),
+        }
+    }
+    if errors.is_empty() {
+        return Ok(patch);
     }
+    Err(errors)
 }
-  
+
+fn taken(field: &str) -> CommonError {
+    match field {
+        "email" => CommonError::new("Email is already in use", 409),
+        _ => CommonError::new("Username is already taken", 409),
+    }
+}
+
+fn stale() -> CommonError {
+    CommonError::new("User was changed by someone else, reload it and try again", 412)
+}
+
+fn check_department(department: Option<String>) -> Result<Option<String>, CommonError> {
+    let department=department.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+    if department.as_ref().is_some_and(|d| d.chars().count() > 100) {
+        return Err(CommonError::validation(vec![FieldError::new("department", "invalid_length", "Department must be at most 100 characters long")]));
+    }
+    Ok(department)
+}
+
 #[async_trait]
 impl UserService for UserServiceImpl {
-    
+    async fn list(&self, filter: FilterUserRequest) -> Result<PageData<User>, CommonError>{
+        check_filter(&filter)?;
+        self.user_repo.get(filter).await.map_err(|e|e.into())
+    }
+    async fn get(&self, id: i32) -> Result<User, CommonError>{
+        self.user_repo.get_by_id(id).await
+            .map_err(|_| CommonError::new("User not found", 404))
+    }
+    async fn create(&self, actor: &UserIdentity, username: String, email: String, password: String, department: Option<String>, role_id: Option<i32>) -> Result<User, CommonError>{
+        let (username, email)=self.check_account(&username, &email, None).await?;
+        let department=check_department(department)?;
+        self.account_service.check_password("password", &password, &username, &email, None).await?;
+        if let Some(role_id)=role_id {
+            let grants=self.authorization_service.get_grants(actor).await?;
+            if !grants.contains(&scope_key("role", &Type::UPDATE)) {
+                return Err(CommonError::new("Granting a role requires the role:UPDATE permission", 403));
+            }
+            self.role_repo.get_by_id(role_id).await
+                .map_err(|_| CommonError::validation(vec![FieldError::new("role_id", "unknown_role", "Role not found")]))?;
+        }
+
+        let password_hash=self.security_service.hash(&password).await?;
+        // the account, its department and its role are written together or not at all
+        let user=match self.user_repo.create_with_role(username, email, password_hash.clone(), department, role_id).await.map_err(|e|e.into())? {
+            UserCreate::Created(mut created) => created.pop().ok_or_else(|| CommonError::new("Can't create user", 500))?,
+            UserCreate::Duplicate(field) => return Err(taken(field)),
+        };
+        if role_id.is_some() {
+            self.authorization_service.invalidate_users(vec![user.id]).await?;
+        }
+        self.account_service.remember_password(user.id, password_hash).await?;
+        if let Err(e)=self.account_service.send_verification(&user).await{
+            tracing::error!("Can't send verification email to user {}: {}", user.id, e.message);
+        }
+        Ok(user)
+    }
+    async fn update(&self, actor: &UserIdentity, id: i32, patch: UserPatch, version: Option<i32>) -> Result<User, CommonError>{
+        let user=self.get(id).await?;
+        if version.is_some_and(|version| version != user.version) {
+            return Err(stale());
+        }
+        let mut patch=self.check_patch(patch, Some(user.id)).await?;
+        if patch.is_empty() {
+            return Ok(user);
+        }
+
+        // switching is_active off is a deactivation and needs what the deactivate endpoint needs
+        let deactivating=user.is_active && patch.is_active == Some(false);
+        if deactivating {
+            if user.id == actor.user_id {
+                return Err(CommonError::new("You can't deactivate your own account", 409));
+            }
+            let grants=self.authorization_service.get_grants(actor).await?;
+            if !grants.contains(&scope_key("user", &Type::DELETE)) {
+                return Err(CommonError::new("Deactivating a user requires the user:DELETE permission", 403));
+            }
+        }
+        let email_changed=patch.email.as_ref().is_some_and(|email| !user.email.eq_ignore_ascii_case(email));
+        if email_changed {
+            patch.email_verified=Some(false);
+        }
+
+        // the version read above guards against a change made while this one was checked
+        let user=match self.user_repo.update(user.id, patch, Some(user.version)).await.map_err(|e|e.into())? {
+            UserUpdate::Updated(user) => user,
+            UserUpdate::Stale => return Err(stale()),
+            UserUpdate::Duplicate(field) => return Err(taken(field)),
+        };
+        if deactivating {
+            self.revoke_sessions(user.id).await?;
+        }
+        if email_changed && !user.is_service_account
+            && let Err(e)=self.account_service.send_verification(&user).await
+        {
+            tracing::error!("Can't send verification email to user {}: {}", user.id, e.message);
+        }
+        Ok(user)
+    }
+    async fn deactivate(&self, actor: &UserIdentity, id: i32) -> Result<User, CommonError>{
+        let user=self.get(id).await?;
+        if user.id == actor.user_id {
+            return Err(CommonError::new("You can't deactivate your own account", 409));
+        }
+        self.user_repo.delete_by_id(user.id).await.map_err(|e|e.into())?;
+        self.revoke_sessions(user.id).await?;
+        self.get(user.id).await
+    }
+    async fn deactivate_many(&self, actor: &UserIdentity, ids: Vec<i32>) -> Result<Vec<i32>, CommonError>{
+        if ids.is_empty() {
+            return Err(CommonError::validation(vec![FieldError::new("user_ids", "required", "At least one user is required")]));
+        }
+        if ids.contains(&actor.user_id) {
+            return Err(CommonError::new("You can't deactivate your own account", 409));
+        }
+        let existing=self.user_repo.get_existing_ids(ids.clone()).await.map_err(|e|e.into())?;
+        let unknown: Vec<String>=ids.iter()
+            .filter(|id| !existing.contains(id))
+            .map(|id| id.to_string())
+            .collect();
+        if !unknown.is_empty() {
+            return Err(CommonError::validation(vec![FieldError::new("user_ids", "unknown_user", format!("Unknown users: {}", unknown.join(", ")))]));
+        }
+
+        let deactivated=self.user_repo.delete_list_ids(existing).await.map_err(|e|e.into())?;
+        for id in &deactivated {
+            self.revoke_sessions(*id).await?;
+        }
+        Ok(deactivated)
+    }
+    async fn reactivate(&self, id: i32) -> Result<User, CommonError>{
+        let user=self.get(id).await?;
+        self.user_repo.reactivate(user.id).await.map_err(|e|e.into())?;
+        self.get(user.id).await
+    }
+    async fn unlock(&self, user_id: i32) -> Result<User, CommonError>{
+        let user=self.user_repo.get_by_id(user_id).await
+            .map_err(|_| CommonError::new("User not found", 404))?;
+        self.user_repo.reset_failed_logins(user.id).await.map_err(|e|e.into())?;
+        self.user_repo.get_by_id(user.id).await.map_err(|e|e.into())
+    }
+    async fn import(&self, actor: &UserIdentity, format: SheetFormat, file: Vec<u8>, dry_run: bool) -> Result<ImportReport, CommonError>{
+        let sheet=Sheet::read(format, &file).map_err(|message| file_error("unreadable", message))?;
+        for column in ["username", "email"] {
+            if !sheet.has_column(column) {
+                return Err(file_error("missing_column", format!("The file has no {} column", column)));
+            }
+        }
+        let lines: Vec<(usize, &Vec<String>)>=sheet.rows.iter()
+            .enumerate()
+            .map(|(index, row)| (index + 2, row))
+            .filter(|(_, row)| row.iter().any(|cell| !cell.is_empty()))
+            .collect();
+        if lines.is_empty() {
+            return Err(file_error("empty", "The file has no users"));
+        }
+        if lines.len() > MAX_IMPORT_ROWS {
+            return Err(file_error("too_many_rows", format!("An import can hold at most {} users", MAX_IMPORT_ROWS)));
+        }
+        let roles=if lines.iter().any(|(_, row)| sheet.cell(row, "role").is_some()) {
+            let grants=self.authorization_service.get_grants(actor).await?;
+            if !grants.contains(&scope_key("role", &Type::UPDATE)) {
+                return Err(CommonError::new("Granting a role requires the role:UPDATE permission", 403));
+            }
+            self.role_repo.get().await.map_err(|e|e.into())?
+        } else {
+            Vec::new()
+        };
+
+        let mut errors=Vec::new();
+        let mut accounts: Vec<(NewAccount, Option<String>)>=Vec::new();
+        // lowercased usernames and emails to the row they were first seen on
+        let mut seen: HashMap<String, usize>=HashMap::new();
+        for (line, row) in &lines {
+            let cell=|name: &str| sheet.cell(row, name);
+            let mut row_errors=Vec::new();
+
+            let employee_id=cell("employee_id").and_then(|value| match value.parse::<i32>() {
+                Ok(employee_id) => Some(employee_id),
+                Err(_) => {
+                    row_errors.push(FieldError::new("employee_id", "invalid_format", "Employee id must be a number"));
+                    None
+                }
+            });
+            let role_id=cell("role").and_then(|value| {
+                let role=roles.iter().find(|role| role.name.eq_ignore_ascii_case(value) || role.id.to_string() == value);
+                if role.is_none() {
+                    row_errors.push(FieldError::new("role", "unknown_role", format!("Role {} not found", value)));
+                }
+                role.map(|role| role.id)
+            });
+            let patch=UserPatch {
+                username: Some(cell("username").unwrap_or_default().to_string()),
+                email: Some(cell("email").unwrap_or_default().to_string()),
+                employee_id: Some(employee_id),
+                department: Some(cell("department").map(str::to_string)),
+                ..Default::default()
+            };
+
+            match check_fields(patch) {
+                Err(field_errors) => row_errors.extend(field_errors),
+                Ok(patch) => {
+                    let username=patch.username.clone().unwrap_or_default();
+                    let email=patch.email.clone().unwrap_or_default();
+                    for (field, value) in [("username", &username), ("email", &email)] {
+                        let first=*seen.entry(format!("{}:{}", field, value.to_lowercase())).or_insert(*line);
+                        if first != *line {
+                            row_errors.push(FieldError::new(field, "duplicate", format!("Same {} as row {}", field, first)));
+                        }
+                    }
+                    if let Some(field)=self.find_taken(&patch, None).await {
+                        row_errors.push(FieldError::new(field, "taken", taken(field).message));
+                    }
+                    let password=cell("password").map(str::to_string);
+                    if let Some(password)=&password
+                        && let Err(e)=self.account_service.check_password("password", password, &username, &email, None).await
+                    {
+                        row_errors.extend(e.errors);
+                    }
+                    let account=NewAccount {
+                        username,
+                        email,
+                        password_hash: String::new(),
+                        employee_id,
+                        department: patch.department.flatten(),
+                        role_id,
+                    };
+                    accounts.push((account, password));
+                }
+            }
+            errors.extend(row_errors.into_iter().map(|error| RowError { row: *line, error }));
+        }
+
+        let mut report=ImportReport { dry_run, rows: lines.len(), created: Vec::new(), errors };
+        if dry_run || !report.errors.is_empty() {
+            return Ok(report);
+        }
+
+        let mut new_accounts=Vec::with_capacity(accounts.len());
+        for (mut account, password) in accounts {
+            account.password_hash=match password {
+                Some(password) => self.security_service.hash(&password).await?,
+                None => "!".to_string(),
+            };
+            new_accounts.push(account);
+        }
+        let granted=new_accounts.iter().any(|account| account.role_id.is_some());
+        let created=match self.user_repo.create_many(new_accounts).await.map_err(|e|e.into())? {
+            UserCreate::Created(created) => created,
+            UserCreate::Duplicate(field) => return Err(taken(field)),
+        };
+        if granted {
+            self.authorization_service.invalidate_users(created.iter().map(|user| user.id).collect()).await?;
+        }
+
+        // the accounts exist now, a mail that can't be sent mustn't fail the import
+        for user in &created {
+            if user.password_hash == "!" {
+                if let Err(e)=self.account_service.request_password_reset(&user.email).await {
+                    tracing::error!("Can't send password link to user {}: {}", user.id, e.message);
+                }
+                continue;
+            }
+            if let Err(e)=self.account_service.remember_password(user.id, user.password_hash.clone()).await {
+                tracing::error!("Can't remember password of user {}: {}", user.id, e.message);
+            }
+            if let Err(e)=self.account_service.send_verification(user).await {
+                tracing::error!("Can't send verification email to user {}: {}", user.id, e.message);
+            }
+        }
+        report.created=created;
+        Ok(report)
+    }
+    async fn export(&self, filter: FilterUserRequest, format: SheetFormat) -> Result<BoxStream<'static, Result<Vec<u8>, CommonError>>, CommonError>{
+        check_filter(&filter)?;
+        if filter.cursor.is_some() {
+            return Err(CommonError::validation(vec![FieldError::new("cursor", "not_supported", "An export always covers every page")]));
+        }
+        let headers: Vec<String>=EXPORT_COLUMNS.iter().map(|column| column.to_string()).collect();
+        let pages=export_pages(self.user_repo.clone(), filter);
+
+        let chunks=match format {
+            SheetFormat::Csv => {
+                let to_csv=|rows: Vec<Vec<String>>| csv_rows(&rows).map_err(|message| CommonError::new(message, 500));
+                let header=to_csv(vec![headers]);
+                stream::once(async move { header })
+                    .chain(pages.map(move |page| page.and_then(|users| to_csv(users.iter().map(export_row).collect()))))
+                    .boxed()
+            }
+            SheetFormat::Xlsx => stream::once(async move {
+                let users: Vec<Vec<User>>=pages.try_collect().await?;
+                let rows=users.iter().flatten().map(export_row).collect();
+                Sheet { headers, rows }.write(SheetFormat::Xlsx).map_err(|message| CommonError::new(message, 500))
+            })
+            .boxed(),
+        };
+        Ok(chunks)
+    }
 }
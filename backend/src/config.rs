@@ -14,4 +14,363 @@ pub struct MysqlConfig{
 }
 fn default_conn()->u32{
     5
-}
\ No newline at end of file
+}
+
+// unset or empty falls back to `default`. A value that doesn't parse stops the startup, many of these are
+// security settings that mustn't quietly turn into something else
+pub(crate) fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    match std::env::var(key) {
+        Ok(value) if !value.trim().is_empty() => value
+            .trim()
+            .parse::<T>()
+            .unwrap_or_else(|_| panic!("Invalid value {:?} for {}", value, key)),
+        _ => default,
+    }
+}
+
+// Argon2id cost parameters used for password hashing
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HashConfig{
+    // memory in KiB
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+impl HashConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            memory_cost: env_or("ARGON2_MEMORY_COST", default.memory_cost),
+            time_cost: env_or("ARGON2_TIME_COST", default.time_cost),
+            parallelism: env_or("ARGON2_PARALLELISM", default.parallelism),
+        }
+    }
+}
+
+impl Default for HashConfig {
+    fn default() -> Self {
+        Self {
+            memory_cost: argon2::Params::DEFAULT_M_COST,
+            time_cost: argon2::Params::DEFAULT_T_COST,
+            parallelism: argon2::Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+// lifetimes in seconds of the access JWT and the opaque refresh token
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TokenConfig{
+    pub access_token_ttl: i64,
+    pub refresh_token_ttl: i64,
+}
+
+impl TokenConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            access_token_ttl: env_or("ACCESS_TOKEN_TTL", default.access_token_ttl),
+            refresh_token_ttl: env_or("REFRESH_TOKEN_TTL", default.refresh_token_ttl),
+        }
+    }
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            access_token_ttl: 15 * 60,
+            refresh_token_ttl: 30 * 24 * 60 * 60,
+        }
+    }
+}
+
+// asymmetric keys used to sign and verify JWTs.
+// JWT_KEYS lists "kid:ALG" pairs (ALG is RS256 or EdDSA), every key needs `<key_dir>/<kid>.pub.pem`
+// and the signing key also `<key_dir>/<kid>.pem`. Retire a key by removing it from the list.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JwtConfig{
+    pub key_dir: String,
+    pub keys: Vec<JwtKeyConfig>,
+    pub signing_kid: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JwtKeyConfig{
+    pub kid: String,
+    pub algorithm: String,
+}
+
+impl JwtConfig {
+    pub fn from_env()->Self{
+        let keys: Vec<JwtKeyConfig> = std::env::var("JWT_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|entry| {
+                let (kid, algorithm) = entry.trim().split_once(':')?;
+                Some(JwtKeyConfig { kid: kid.trim().to_string(), algorithm: algorithm.trim().to_string() })
+            })
+            .collect();
+        let signing_kid = std::env::var("JWT_SIGNING_KID")
+            .ok()
+            .or_else(|| keys.first().map(|key| key.kid.clone()))
+            .unwrap_or_default();
+        Self {
+            key_dir: std::env::var("JWT_KEY_DIR").unwrap_or("keys".to_string()),
+            keys,
+            signing_kid,
+        }
+    }
+}
+
+// brute-force protection for /login
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoginThrottleConfig{
+    // failed passwords before an account is locked
+    pub max_failed_attempts: i32,
+    pub lockout_seconds: i64,
+    // failures from one IP within `ip_window_seconds` before it is refused
+    pub ip_max_failed_attempts: i64,
+    pub ip_window_seconds: i64,
+    // delay after a failure doubles with every previous failure, up to `max_delay_ms`
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl LoginThrottleConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            max_failed_attempts: env_or("LOGIN_MAX_FAILED_ATTEMPTS", default.max_failed_attempts),
+            lockout_seconds: env_or("LOGIN_LOCKOUT_SECONDS", default.lockout_seconds),
+            ip_max_failed_attempts: env_or("LOGIN_IP_MAX_FAILED_ATTEMPTS", default.ip_max_failed_attempts),
+            ip_window_seconds: env_or("LOGIN_IP_WINDOW_SECONDS", default.ip_window_seconds),
+            base_delay_ms: env_or("LOGIN_BASE_DELAY_MS", default.base_delay_ms),
+            max_delay_ms: env_or("LOGIN_MAX_DELAY_MS", default.max_delay_ms),
+        }
+    }
+
+    pub fn delay(&self, failures: i64) -> std::time::Duration {
+        let exponent = failures.saturating_sub(1).clamp(0, 16) as u32;
+        let delay = self.base_delay_ms.saturating_mul(1 << exponent).min(self.max_delay_ms);
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+impl Default for LoginThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_failed_attempts: 5,
+            lockout_seconds: 15 * 60,
+            ip_max_failed_attempts: 20,
+            ip_window_seconds: 15 * 60,
+            base_delay_ms: 250,
+            max_delay_ms: 4000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MfaConfig{
+    // shown by authenticator apps next to the account name
+    pub issuer: String,
+    // lifetime in seconds of the "mfa pending" token returned by /login
+    pub pending_token_ttl: i64,
+}
+
+impl MfaConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            issuer: std::env::var("MFA_ISSUER").unwrap_or(default.issuer),
+            pending_token_ttl: env_or("MFA_PENDING_TOKEN_TTL", default.pending_token_ttl),
+        }
+    }
+}
+
+impl Default for MfaConfig {
+    fn default() -> Self {
+        Self {
+            issuer: "fullstack-dioxus".to_string(),
+            pending_token_ttl: 5 * 60,
+        }
+    }
+}
+
+// outgoing email, see domain::mail
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MailConfig{
+    // "smtp" or "file"
+    pub transport: String,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: String,
+    pub smtp_password: String,
+    // "starttls", "tls" or "none"
+    pub smtp_tls: String,
+    // where the file mailer writes .eml files
+    pub file_dir: String,
+    // file mailer also logs bodies at debug. They hold reset and verification links, local development only
+    pub log_body: bool,
+    // frontend the links in emails point to
+    pub app_url: String,
+}
+
+impl MailConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            transport: std::env::var("MAIL_TRANSPORT").unwrap_or(default.transport),
+            from: std::env::var("MAIL_FROM").unwrap_or(default.from),
+            smtp_host: std::env::var("SMTP_HOST").unwrap_or(default.smtp_host),
+            smtp_port: env_or("SMTP_PORT", default.smtp_port),
+            smtp_username: std::env::var("SMTP_USERNAME").unwrap_or(default.smtp_username),
+            smtp_password: std::env::var("SMTP_PASSWORD").unwrap_or(default.smtp_password),
+            smtp_tls: std::env::var("SMTP_TLS").unwrap_or(default.smtp_tls),
+            file_dir: std::env::var("MAIL_FILE_DIR").unwrap_or(default.file_dir),
+            log_body: env_or("MAIL_LOG_BODY", default.log_body),
+            app_url: std::env::var("APP_URL").unwrap_or(default.app_url),
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            transport: "file".to_string(),
+            from: "fullstack-dioxus <no-reply@localhost>".to_string(),
+            smtp_host: "localhost".to_string(),
+            smtp_port: 587,
+            smtp_username: String::new(),
+            smtp_password: String::new(),
+            smtp_tls: "starttls".to_string(),
+            file_dir: String::new(),
+            log_body: false,
+            app_url: "http://localhost:8080".to_string(),
+        }
+    }
+}
+
+// password reset and email verification links
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccountConfig{
+    // lifetimes in seconds of the emailed tokens
+    pub password_reset_ttl: i64,
+    pub email_verification_ttl: i64,
+    // refuse login until the email address is verified
+    pub require_verified_email: bool,
+}
+
+impl AccountConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            password_reset_ttl: env_or("PASSWORD_RESET_TTL", default.password_reset_ttl),
+            email_verification_ttl: env_or("EMAIL_VERIFICATION_TTL", default.email_verification_ttl),
+            require_verified_email: env_or("REQUIRE_VERIFIED_EMAIL", default.require_verified_email),
+        }
+    }
+}
+
+impl Default for AccountConfig {
+    fn default() -> Self {
+        Self {
+            password_reset_ttl: 60 * 60,
+            email_verification_ttl: 24 * 60 * 60,
+            require_verified_email: false,
+        }
+    }
+}
+
+// rules for new passwords (register, reset and change)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PasswordPolicyConfig{
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    // against the bundled list of common passwords
+    pub reject_common: bool,
+    // password containing the username or email
+    pub reject_user_info: bool,
+    // how many previous passwords can't be set again, 0 turns the check off
+    pub history_size: i64,
+}
+
+impl PasswordPolicyConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", default.min_length),
+            max_length: env_or("PASSWORD_MAX_LENGTH", default.max_length),
+            require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", default.require_lowercase),
+            require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", default.require_uppercase),
+            require_digit: env_or("PASSWORD_REQUIRE_DIGIT", default.require_digit),
+            require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", default.require_symbol),
+            reject_common: env_or("PASSWORD_REJECT_COMMON", default.reject_common),
+            reject_user_info: env_or("PASSWORD_REJECT_USER_INFO", default.reject_user_info),
+            history_size: env_or("PASSWORD_HISTORY_SIZE", default.history_size),
+        }
+    }
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: false,
+            reject_common: true,
+            reject_user_info: true,
+            history_size: 5,
+        }
+    }
+}
+
+// effective permissions are cached per user for `cache_ttl` seconds,
+// write paths of roles, permissions and grants invalidate them right away
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthorizationConfig{
+    pub cache_ttl: i64,
+    // denials are always written to `audit_logs`, allowed requests only when set
+    pub audit_allowed: bool,
+}
+
+impl AuthorizationConfig {
+    pub fn from_env()->Self{
+        let default = Self::default();
+        Self {
+            cache_ttl: env_or("PERMISSION_CACHE_TTL", default.cache_ttl),
+            audit_allowed: env_or("AUDIT_ALLOWED_DECISIONS", default.audit_allowed),
+        }
+    }
+}
+
+impl Default for AuthorizationConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl: 5 * 60,
+            audit_allowed: true,
+        }
+    }
+}
+
+// first administrator of a fresh deployment, see the README
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BootstrapConfig{
+    // username or email of an existing user, given the admin role at startup while nobody holds it
+    pub admin: String,
+}
+
+impl BootstrapConfig {
+    pub fn from_env()->Self{
+        Self {
+            admin: std::env::var("BOOTSTRAP_ADMIN").unwrap_or_default().trim().to_string(),
+        }
+    }
+}
@@ -1,33 +1,200 @@
 use diesel::prelude::*;
-use crate::domain::permission::repo::{Action, Type};
-use super::schema::{actions};
+use common_model::page::{PageData, PageRequest};
+use serde_json::json;
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
+use crate::domain::error::RepoError;
+use crate::domain::permission::repo::{Action, ActionRepo, Type};
+use super::schema::{actions, permission_actions};
+use super::cursor::{KeyColumn, Keyset, Paging};
+use super::pool::{self, DbConn};
 use std::str::FromStr;
+use std::sync::Arc;
 
 #[derive(Debug,Queryable,Selectable)]
 #[diesel(table_name=actions)]
 #[diesel(check_for_backend(diesel::mysql::Mysql))]
 pub struct ActionDiesel{
     pub id: i32,
-    pub key: String,//CREATE,UPDATE,DELETE,READ
+    pub key: String,//CREATE,UPDATE,DELETE,READ or a custom key
     pub description: Option<String>
 }
 
-impl From<ActionDiesel> for Action{
-    fn from(value: ActionDiesel) -> Self {
-        Action { 
-            id: value.id, 
-            key: Type::from_str(&value.key).unwrap_or(Type::READ),
+// a malformed key is an error, it must never fall back to another action
+impl TryFrom<ActionDiesel> for Action{
+    type Error = RepoError;
+
+    fn try_from(value: ActionDiesel) -> Result<Self, Self::Error> {
+        let key = Type::from_str(&value.key)
+            .map_err(|_| RepoError{message: format!("Invalid action key {} in actions {}", value.key, value.id)})?;
+        Ok(Action {
+            id: value.id,
+            key,
             description: value.description.unwrap_or("".to_owned())
-        }
+        })
     }
 }
 
 impl From<Action> for ActionDiesel {
     fn from(value: Action) -> Self {
-        ActionDiesel { 
-            id: value.id, 
-            key: value.key.to_string(), 
+        ActionDiesel {
+            id: value.id,
+            key: value.key.to_string(),
             description: Some(value.description)
         }
     }
 }
+
+#[derive(Insertable)]
+#[diesel(table_name=actions)]
+pub struct NewAction {
+    pub key: String,
+    pub description: Option<String>
+}
+
+// impl repo
+pub struct ActionDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl ActionDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        ActionDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActionRepo for ActionDieselImpl {
+    async fn get(&self) -> Result<Vec<Action>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = actions::table
+                .order_by(actions::id)
+                .load::<ActionDiesel>(&mut conn)?;
+
+            result.into_iter().map(Action::try_from).collect()
+        })
+        .await?
+    }
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Action>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let keyset = Keyset::new("id", vec![KeyColumn::new::<_, i32>(actions::id, false)]);
+
+            let total = actions::table.count().get_result::<i64>(&mut conn)?;
+            let mut query = actions::table.into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<ActionDiesel>(&mut conn)?
+                .into_iter()
+                .map(Action::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(paging.page(result, total, &keyset, |action| vec![json!(action.id)]))
+        })
+        .await?
+    }
+    async fn get_by_id(&self, id: i32) -> Result<Action, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = actions::table
+                .find(id)
+                .first::<ActionDiesel>(&mut conn)?;
+
+            result.try_into()
+        })
+        .await?
+    }
+    async fn get_by_key(&self, key: String) -> Result<Option<Action>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = actions::table
+                .filter(actions::key.eq(key))
+                .first::<ActionDiesel>(&mut conn)
+                .optional()?;
+
+            result.map(Action::try_from).transpose()
+        })
+        .await?
+    }
+    async fn create(&self, key: String, description: String) -> Result<Action, RepoError>{
+        let pool = self.pool.clone();
+        let inserted_id = pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let new_action = NewAction { key, description: Some(description) };
+
+            let result = diesel::insert_into(actions::table)
+                .values(&new_action)
+                .execute(&mut conn)?;
+            if result == 0 {
+                return Err(RepoError{message:"Can't inserted".to_string()});
+            }
+            let id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+                "LAST_INSERT_ID()",
+            ))
+            .get_result::<i32>(&mut conn)?;
+
+            Ok(id)
+        })
+        .await??;
+
+        self.get_by_id(inserted_id).await
+    }
+    async fn update(&self, id: i32, description: String) -> Result<Action, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(actions::table.find(id))
+                .set(actions::description.eq(description))
+                .execute(&mut conn)?;
+
+            let action_update = actions::table
+                .find(id)
+                .first::<ActionDiesel>(&mut conn)?;
+
+            action_update.try_into()
+        })
+        .await?
+    }
+    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::delete(actions::table.find(id))
+                .execute(&mut conn)?;
+
+            if result==0{
+                return Err(RepoError{message:"Can't Delete".to_string()});
+            }
+            Ok(id)
+        })
+        .await?
+    }
+    async fn count_usage(&self, id: i32) -> Result<i64, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let count = permission_actions::table
+                .filter(permission_actions::action_id.eq(id))
+                .count()
+                .get_result::<i64>(&mut conn)?;
+            Ok(count)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,131 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use crate::domain::error::RepoError;
+use crate::domain::security::action_token::{ActionPurpose, ActionTokenRepo};
+use super::schema::{user_action_tokens, users};
+use super::pool::{self, DbConn};
+use std::sync::Arc;
+
+#[derive(Insertable)]
+#[diesel(table_name=user_action_tokens)]
+pub struct NewActionToken {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub purpose: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+}
+
+// marks a valid token used and returns its user, call inside a transaction
+fn consume_token(conn: &mut MysqlConnection, token_hash: String, purpose: ActionPurpose) -> Result<Option<i32>, RepoError> {
+    let now = chrono::Utc::now().naive_utc();
+    let token = user_action_tokens::table
+        .filter(user_action_tokens::token_hash.eq(token_hash))
+        .filter(user_action_tokens::purpose.eq(purpose.as_str()))
+        .filter(user_action_tokens::used_at.is_null())
+        .filter(user_action_tokens::expires_at.gt(now))
+        .select((user_action_tokens::id, user_action_tokens::user_id))
+        .for_update()
+        .first::<(i32, i32)>(conn)
+        .optional()?;
+    let Some((id, user_id)) = token else {
+        return Ok(None);
+    };
+
+    diesel::update(user_action_tokens::table.find(id))
+        .set(user_action_tokens::used_at.eq(Some(now)))
+        .execute(conn)?;
+    Ok(Some(user_id))
+}
+
+// impl repo
+pub struct ActionTokenDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl ActionTokenDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        ActionTokenDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ActionTokenRepo for ActionTokenDieselImpl {
+    async fn create(&self, user_id: i32, purpose: ActionPurpose, token_hash: String, expires_at: NaiveDateTime) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                // only the latest link sent for a purpose works
+                diesel::update(user_action_tokens::table)
+                    .filter(user_action_tokens::user_id.eq(user_id))
+                    .filter(user_action_tokens::purpose.eq(purpose.as_str()))
+                    .filter(user_action_tokens::used_at.is_null())
+                    .set(user_action_tokens::used_at.eq(Some(now)))
+                    .execute(conn)?;
+
+                let token = NewActionToken {
+                    user_id,
+                    token_hash,
+                    purpose: purpose.as_str().to_string(),
+                    created_at: Some(now),
+                    expires_at,
+                };
+                let result = diesel::insert_into(user_action_tokens::table)
+                    .values(&token)
+                    .execute(conn)?;
+                if result == 0 {
+                    return Err(RepoError{message:"Can't inserted".to_string()});
+                }
+                Ok(())
+            })
+        })
+        .await?
+    }
+    async fn find(&self, token_hash: String, purpose: ActionPurpose) -> Result<Option<i32>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
+
+            let user_id = user_action_tokens::table
+                .filter(user_action_tokens::token_hash.eq(token_hash))
+                .filter(user_action_tokens::purpose.eq(purpose.as_str()))
+                .filter(user_action_tokens::used_at.is_null())
+                .filter(user_action_tokens::expires_at.gt(now))
+                .select(user_action_tokens::user_id)
+                .first::<i32>(&mut conn)
+                .optional()?;
+            Ok(user_id)
+        })
+        .await?
+    }
+    async fn consume(&self, token_hash: String, purpose: ActionPurpose) -> Result<Option<i32>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| consume_token(conn, token_hash, purpose))
+        })
+        .await?
+    }
+    async fn consume_for_password(&self, token_hash: String, password_hash: String) -> Result<Option<i32>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                let Some(user_id) = consume_token(conn, token_hash, ActionPurpose::PasswordReset)? else {
+                    return Ok(None);
+                };
+                diesel::update(users::table.find(user_id))
+                    .set(users::password_hash.eq(password_hash))
+                    .execute(conn)?;
+                Ok(Some(user_id))
+            })
+        })
+        .await?
+    }
+}
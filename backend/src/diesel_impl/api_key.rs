@@ -0,0 +1,177 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde_json::json;
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
+use crate::domain::error::RepoError;
+use crate::domain::security::api_key::{ApiKey, ApiKeyRepo, NewApiKey};
+use super::schema::api_keys;
+use super::cursor::{KeyColumn, Keyset, Paging};
+use super::pool::{self, DbConn};
+use std::sync::Arc;
+
+#[derive(Debug,Queryable,Selectable)]
+#[diesel(table_name=api_keys)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct ApiKeyDiesel{
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: String,//user:READ,role:UPDATE
+    pub created_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked: bool,
+}
+
+impl From<ApiKeyDiesel> for ApiKey {
+    fn from(value: ApiKeyDiesel) -> Self {
+        ApiKey {
+            id: value.id,
+            user_id: value.user_id,
+            name: value.name,
+            prefix: value.prefix,
+            scopes: value
+                .scopes
+                .split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect(),
+            created_at: value.created_at.unwrap_or_default(),
+            last_used_at: value.last_used_at,
+            expires_at: value.expires_at,
+            revoked: value.revoked,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name=api_keys)]
+pub struct NewApiKeyDiesel {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+// impl repo
+pub struct ApiKeyDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl ApiKeyDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        ApiKeyDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyRepo for ApiKeyDieselImpl {
+    async fn create(&self, key: NewApiKey) -> Result<ApiKey, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let new_key = NewApiKeyDiesel {
+                user_id: key.user_id,
+                name: key.name,
+                prefix: key.prefix,
+                key_hash: key.key_hash,
+                scopes: key.scopes.join(","),
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                expires_at: key.expires_at,
+            };
+            let result = diesel::insert_into(api_keys::table)
+                .values(&new_key)
+                .execute(&mut conn)?;
+            if result == 0 {
+                return Err(RepoError{message:"Can't inserted".to_string()});
+            }
+            let id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+                "LAST_INSERT_ID()",
+            ))
+            .get_result::<i32>(&mut conn)?;
+
+            let inserted = api_keys::table
+                .find(id)
+                .first::<ApiKeyDiesel>(&mut conn)?;
+            Ok(inserted.into())
+        })
+        .await?
+    }
+    async fn get_by_hash(&self, key_hash: String) -> Result<Option<ApiKey>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = api_keys::table
+                .filter(api_keys::key_hash.eq(key_hash))
+                .first::<ApiKeyDiesel>(&mut conn)
+                .optional()?;
+            Ok(result.map(|key| key.into()))
+        })
+        .await?
+    }
+    async fn get_by_user_id(&self, user_id: i32, page: PageRequest) -> Result<PageData<ApiKey>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "-id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let keyset = Keyset::new("-id", vec![KeyColumn::new::<_, i32>(api_keys::id, true)]);
+
+            let total = api_keys::table
+                .filter(api_keys::user_id.eq(user_id))
+                .count()
+                .get_result::<i64>(&mut conn)?;
+            let mut query = api_keys::table
+                .filter(api_keys::user_id.eq(user_id))
+                .into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<ApiKeyDiesel>(&mut conn)?;
+
+            Ok(paging
+                .page(result, total, &keyset, |key| vec![json!(key.id)])
+                .map(|key| key.into()))
+        })
+        .await?
+    }
+    async fn touch(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(api_keys::table.find(id))
+                .set(api_keys::last_used_at.eq(Some(chrono::Utc::now().naive_utc())))
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(api_keys::table.find(id))
+                .filter(api_keys::user_id.eq(user_id))
+                .filter(api_keys::revoked.eq(false))
+                .set(api_keys::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,64 @@
+use diesel::prelude::*;
+use crate::domain::audit::repo::{AuditRepo, NewAuditLog};
+use crate::domain::error::RepoError;
+use super::schema::audit_logs;
+use super::pool::{self, DbConn};
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+
+#[derive(Insertable)]
+#[diesel(table_name=audit_logs)]
+pub struct NewAuditLogDiesel {
+    pub user_id: i32,
+    pub api_key_id: Option<i32>,
+    pub resource: String,
+    pub action: String,
+    pub resource_id: Option<String>,
+    pub allowed: bool,
+    pub reason: String,
+    pub method: String,
+    pub path: String,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+// impl repo
+pub struct AuditDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl AuditDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        AuditDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepo for AuditDieselImpl {
+    async fn record(&self, entry: NewAuditLog) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            // path and target come from the request, keep them inside their columns
+            let log = NewAuditLogDiesel {
+                user_id: entry.user_id,
+                api_key_id: entry.api_key_id,
+                resource: entry.resource,
+                action: entry.action,
+                resource_id: entry.resource_id.map(|id| id.chars().take(100).collect()),
+                allowed: entry.allowed,
+                reason: entry.reason,
+                method: entry.method,
+                path: entry.path.chars().take(255).collect(),
+                ip_address: entry.ip_address,
+                created_at: chrono::Utc::now().naive_utc(),
+            };
+            diesel::insert_into(audit_logs::table)
+                .values(&log)
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+}
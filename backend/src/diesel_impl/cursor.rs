@@ -0,0 +1,201 @@
+use common_model::page::PageData;
+use diesel::dsl;
+use diesel::expression::expression_types::NotSelectable;
+use diesel::expression::{AsExpression, BoxableExpression};
+use diesel::mysql::Mysql;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, SqlType};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::domain::cursor::Cursor;
+use crate::domain::error::RepoError;
+
+// keyset pagination shared by the list queries. A page continues from the sort key values of
+// the row next to it instead of skipping `offset` rows, so it stays fast on large tables and
+// doesn't shift when rows are inserted or deleted meanwhile
+
+pub type Condition<QS> = Box<dyn BoxableExpression<QS, Mysql, SqlType = Bool>>;
+pub type Ordering<QS> = Box<dyn BoxableExpression<QS, Mysql, SqlType = NotSelectable>>;
+
+fn invalid_cursor() -> RepoError {
+    RepoError{message: "Cursor doesn't match the sort".to_string()}
+}
+
+type Compare<QS> = Box<dyn Fn(&Value, bool) -> Result<(Condition<QS>, Condition<QS>), RepoError> + Send + Sync>;
+type Order<QS> = Box<dyn Fn(bool) -> Ordering<QS> + Send + Sync>;
+
+// one sort column of a keyset
+pub struct KeyColumn<QS> {
+    descending: bool,
+    // (column past the value, column equal to it), past meaning greater when the flag is set.
+    // None for nullable columns, NULLs would fall out of every comparison
+    compare: Option<Compare<QS>>,
+    order: Order<QS>,
+}
+
+impl<QS: 'static> KeyColumn<QS> {
+    // `T` is the Rust type cursor values of the column are read back as
+    pub fn new<C, T>(column: C, descending: bool) -> Self
+    where
+        C: ExpressionMethods + Copy + Send + Sync + 'static,
+        C::SqlType: SqlType,
+        T: DeserializeOwned + AsExpression<C::SqlType> + Send + 'static,
+        dsl::Gt<C, T>: BoxableExpression<QS, Mysql, SqlType = Bool> + 'static,
+        dsl::Lt<C, T>: BoxableExpression<QS, Mysql, SqlType = Bool> + 'static,
+        dsl::Eq<C, T>: BoxableExpression<QS, Mysql, SqlType = Bool> + 'static,
+        dsl::Asc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+        dsl::Desc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+    {
+        let compare = move |value: &Value, greater: bool| -> Result<(Condition<QS>, Condition<QS>), RepoError> {
+            let parse = || serde_json::from_value::<T>(value.clone()).map_err(|_| invalid_cursor());
+            let past: Condition<QS> = if greater {
+                Box::new(column.gt(parse()?))
+            } else {
+                Box::new(column.lt(parse()?))
+            };
+            Ok((past, Box::new(column.eq(parse()?))))
+        };
+        Self { descending, compare: Some(Box::new(compare)), order: order(column) }
+    }
+
+    // a column that only orders the rows, lists sorted by it are numbered and carry no cursors
+    pub fn order_only<C>(column: C, descending: bool) -> Self
+    where
+        C: ExpressionMethods + Copy + Send + Sync + 'static,
+        dsl::Asc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+        dsl::Desc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+    {
+        Self { descending, compare: None, order: order(column) }
+    }
+}
+
+fn order<QS, C>(column: C) -> Order<QS>
+where
+    C: ExpressionMethods + Copy + Send + Sync + 'static,
+    dsl::Asc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+    dsl::Desc<C>: BoxableExpression<QS, Mysql, SqlType = NotSelectable> + 'static,
+{
+    Box::new(move |descending: bool| -> Ordering<QS> {
+        if descending {
+            Box::new(column.desc())
+        } else {
+            Box::new(column.asc())
+        }
+    })
+}
+
+// ORDER BY and WHERE of a keyset page. The last column must be unique, usually the id
+pub struct Keyset<QS> {
+    // identifies the order, cursors of another one are refused
+    sort: String,
+    columns: Vec<KeyColumn<QS>>,
+}
+
+impl<QS: 'static> Keyset<QS> {
+    pub fn new(sort: impl Into<String>, columns: Vec<KeyColumn<QS>>) -> Self {
+        Self { sort: sort.into(), columns }
+    }
+
+    // false when a column only orders, see KeyColumn::order_only
+    pub fn cursors(&self) -> bool {
+        self.columns.iter().all(|column| column.compare.is_some())
+    }
+
+    // rows past `cursor` in scan order: k0 > v0 or (k0 = v0 and (k1 > v1 or (k1 = v1 and ...)))
+    pub fn after(&self, cursor: &Cursor) -> Result<Condition<QS>, RepoError> {
+        if cursor.keys.len() != self.columns.len() {
+            return Err(invalid_cursor());
+        }
+        let mut condition: Option<Condition<QS>> = None;
+        for (column, value) in self.columns.iter().zip(&cursor.keys).rev() {
+            // walking backwards flips every comparison
+            let greater = column.descending == cursor.backward;
+            let compare = column.compare.as_ref().ok_or_else(invalid_cursor)?;
+            let (past, equal) = compare(value, greater)?;
+            condition = Some(match condition {
+                None => past,
+                Some(rest) => Box::new(past.or(equal.and(rest))),
+            });
+        }
+        condition.ok_or_else(invalid_cursor)
+    }
+
+    // walking backwards reads the rows in reverse and flips them afterwards
+    pub fn order(&self, backward: bool) -> Vec<Ordering<QS>> {
+        self.columns
+            .iter()
+            .map(|column| (column.order)(column.descending != backward))
+            .collect()
+    }
+}
+
+// how a list query is paged
+pub enum Paging {
+    Offset { page_num: i64, page_size: i64 },
+    Keyset { cursor: Cursor, page_size: i64 },
+}
+
+impl Paging {
+    // a cursor wins over the page number. Err when the cursor doesn't belong to `sort`
+    pub fn new(page_num: Option<i64>, page_size: i64, cursor: Option<&str>, sort: &str) -> Result<Self, RepoError> {
+        let page_size = page_size.max(1);
+        match cursor.map(str::trim).filter(|cursor| !cursor.is_empty()) {
+            Some(cursor) => {
+                let cursor = Cursor::decode(cursor, sort).map_err(|message| RepoError{message})?;
+                Ok(Paging::Keyset { cursor, page_size })
+            }
+            None => Ok(Paging::Offset { page_num: page_num.unwrap_or(1).max(1), page_size }),
+        }
+    }
+
+    pub fn backward(&self) -> bool {
+        matches!(self, Paging::Keyset { cursor, .. } if cursor.backward)
+    }
+
+    // rows to fetch, one more than the page to see whether another follows
+    pub fn limit(&self) -> i64 {
+        match self {
+            Paging::Offset { page_size, .. } | Paging::Keyset { page_size, .. } => page_size + 1,
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        match self {
+            Paging::Offset { page_num, page_size } => (page_num - 1) * page_size,
+            Paging::Keyset { .. } => 0,
+        }
+    }
+
+    // WHERE to add to the query, None for numbered pages
+    pub fn condition<QS: 'static>(&self, keyset: &Keyset<QS>) -> Result<Option<Condition<QS>>, RepoError> {
+        match self {
+            Paging::Offset { .. } => Ok(None),
+            Paging::Keyset { cursor, .. } => keyset.after(cursor).map(Some),
+        }
+    }
+
+    // turns the `limit()` rows that were fetched into the page, `keys` gives the cursor
+    // values of a row in the order of the keyset columns
+    pub fn page<R, QS: 'static>(self, mut rows: Vec<R>, total: i64, keyset: &Keyset<QS>, keys: impl Fn(&R) -> Vec<Value>) -> PageData<R> {
+        let (page_num, page_size) = match &self {
+            Paging::Offset { page_num, page_size } => (*page_num, *page_size),
+            Paging::Keyset { page_size, .. } => (0, *page_size),
+        };
+        let more = rows.len() as i64 > page_size;
+        rows.truncate(page_size as usize);
+        let (has_before, has_after) = match &self {
+            Paging::Offset { page_num, .. } => (*page_num > 1, more),
+            Paging::Keyset { cursor, .. } if cursor.backward => {
+                rows.reverse();
+                (more, true)
+            }
+            Paging::Keyset { .. } => (true, more),
+        };
+
+        let cursor = |row: &R, backward: bool| Cursor { sort: keyset.sort.clone(), keys: keys(row), backward }.encode();
+        let next_cursor = rows.last().filter(|_| keyset.cursors() && has_after).map(|row| cursor(row, false));
+        let prev_cursor = rows.first().filter(|_| keyset.cursors() && has_before).map(|row| cursor(row, true));
+        PageData { page_num, page_size, total, data: rows, next_cursor, prev_cursor }
+    }
+}
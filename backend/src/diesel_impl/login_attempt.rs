@@ -0,0 +1,67 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use crate::domain::error::RepoError;
+use crate::domain::security::login_attempt::LoginAttemptRepo;
+use super::schema::login_attempts;
+use super::pool::{self, DbConn};
+use std::sync::Arc;
+
+#[derive(Insertable)]
+#[diesel(table_name=login_attempts)]
+pub struct NewLoginAttempt {
+    pub user_id: Option<i32>,
+    pub identifier: String,
+    pub ip_address: Option<String>,
+    pub succeeded: bool,
+    pub attempted_at: NaiveDateTime,
+}
+
+// impl repo
+pub struct LoginAttemptDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl LoginAttemptDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        LoginAttemptDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginAttemptRepo for LoginAttemptDieselImpl {
+    async fn record(&self, user_id: Option<i32>, identifier: String, ip_address: Option<String>, succeeded: bool) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let attempt = NewLoginAttempt {
+                user_id,
+                // identifiers are whatever the client typed, keep them inside the column
+                identifier: identifier.chars().take(255).collect(),
+                ip_address,
+                succeeded,
+                attempted_at: chrono::Utc::now().naive_utc(),
+            };
+            diesel::insert_into(login_attempts::table)
+                .values(&attempt)
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn count_failures_by_ip(&self, ip_address: String, since: NaiveDateTime) -> Result<i64, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = login_attempts::table
+                .filter(login_attempts::ip_address.eq(ip_address))
+                .filter(login_attempts::succeeded.eq(false))
+                .filter(login_attempts::attempted_at.ge(since))
+                .count()
+                .get_result::<i64>(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+}
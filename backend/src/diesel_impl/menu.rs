@@ -0,0 +1,75 @@
+use diesel::prelude::*;
+use crate::domain::error::RepoError;
+use crate::domain::menu::repo::{MenuItem, MenuLabel, MenuRepo};
+use crate::domain::permission::repo::Type;
+use super::schema::menu_items;
+use super::pool::{self, DbConn};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug,Queryable,Selectable)]
+#[diesel(table_name=menu_items)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct MenuItemDiesel{
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub code: String,
+    pub label_en_us: String,
+    pub label_zh_cn: String,
+    pub icon: Option<String>,
+    pub path: String,
+    pub resource: Option<String>,
+    pub action: Option<String>,
+    pub sort_order: i32,
+}
+
+// an item guarded by a malformed action must not turn into a visible one
+impl TryFrom<MenuItemDiesel> for MenuItem{
+    type Error = RepoError;
+
+    fn try_from(value: MenuItemDiesel) -> Result<Self, Self::Error> {
+        let action = value.action
+            .map(|action| Type::from_str(&action)
+                .map_err(|_| RepoError{message: format!("Invalid action {} in menu_items {}", action, value.id)}))
+            .transpose()?;
+        Ok(MenuItem {
+            id: value.id,
+            parent_id: value.parent_id,
+            code: value.code,
+            label: MenuLabel { zh_cn: value.label_zh_cn, en_us: value.label_en_us },
+            icon: value.icon,
+            path: value.path,
+            resource: value.resource,
+            action,
+            sort_order: value.sort_order,
+        })
+    }
+}
+
+// impl repo
+pub struct MenuDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl MenuDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        MenuDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl MenuRepo for MenuDieselImpl {
+    async fn get(&self) -> Result<Vec<MenuItem>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = menu_items::table
+                .order_by((menu_items::sort_order, menu_items::id))
+                .load::<MenuItemDiesel>(&mut conn)?;
+
+            result.into_iter().map(MenuItem::try_from).collect()
+        })
+        .await?
+    }
+}
@@ -0,0 +1,160 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use crate::domain::error::RepoError;
+use crate::domain::security::mfa::{MfaRepo, UserMfa};
+use super::schema::{mfa_recovery_codes, user_mfa};
+use super::pool::{self, DbConn};
+use std::sync::Arc;
+
+#[derive(Debug,Queryable,Selectable,Insertable)]
+#[diesel(table_name=user_mfa)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct UserMfaDiesel{
+    pub user_id: i32,
+    pub secret: String,
+    pub enabled: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub confirmed_at: Option<NaiveDateTime>,
+    pub last_used_step: Option<i64>,
+}
+
+impl From<UserMfaDiesel> for UserMfa {
+    fn from(value: UserMfaDiesel) -> Self {
+        UserMfa {
+            user_id: value.user_id,
+            secret: value.secret,
+            enabled: value.enabled,
+            created_at: value.created_at.unwrap_or_default(),
+            confirmed_at: value.confirmed_at,
+            last_used_step: value.last_used_step,
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name=mfa_recovery_codes)]
+pub struct NewRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
+
+// impl repo
+pub struct MfaDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl MfaDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        MfaDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl MfaRepo for MfaDieselImpl {
+    async fn get(&self, user_id: i32) -> Result<Option<UserMfa>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = user_mfa::table
+                .find(user_id)
+                .first::<UserMfaDiesel>(&mut conn)
+                .optional()?;
+
+            Ok(result.map(|mfa| mfa.into()))
+        })
+        .await?
+    }
+    async fn save_secret(&self, user_id: i32, secret: String) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let mfa = UserMfaDiesel {
+                user_id,
+                secret,
+                enabled: false,
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                confirmed_at: None,
+                last_used_step: None,
+            };
+            diesel::replace_into(user_mfa::table)
+                .values(&mfa)
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn enable(&self, user_id: i32, recovery_code_hashes: Vec<String>) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                let result = diesel::update(user_mfa::table.find(user_id))
+                    .set((
+                        user_mfa::enabled.eq(true),
+                        user_mfa::confirmed_at.eq(Some(chrono::Utc::now().naive_utc())),
+                    ))
+                    .execute(conn)?;
+                if result == 0 {
+                    return Err(RepoError{message:"Can't updated".to_string()});
+                }
+
+                diesel::delete(mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)))
+                    .execute(conn)?;
+                let codes: Vec<NewRecoveryCode> = recovery_code_hashes
+                    .into_iter()
+                    .map(|code_hash| NewRecoveryCode { user_id, code_hash })
+                    .collect();
+                diesel::insert_into(mfa_recovery_codes::table)
+                    .values(&codes)
+                    .execute(conn)?;
+                Ok(())
+            })
+        })
+        .await?
+    }
+    async fn use_step(&self, user_id: i32, step: i64) -> Result<bool, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(user_mfa::table.find(user_id))
+                .filter(user_mfa::last_used_step.is_null().or(user_mfa::last_used_step.lt(step)))
+                .set(user_mfa::last_used_step.eq(Some(step)))
+                .execute(&mut conn)?;
+            Ok(result == 1)
+        })
+        .await?
+    }
+    async fn use_recovery_code(&self, user_id: i32, code_hash: String) -> Result<bool, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(mfa_recovery_codes::table)
+                .filter(mfa_recovery_codes::user_id.eq(user_id))
+                .filter(mfa_recovery_codes::code_hash.eq(code_hash))
+                .filter(mfa_recovery_codes::used_at.is_null())
+                .set(mfa_recovery_codes::used_at.eq(Some(chrono::Utc::now().naive_utc())))
+                .execute(&mut conn)?;
+            Ok(result > 0)
+        })
+        .await?
+    }
+    async fn delete(&self, user_id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                diesel::delete(mfa_recovery_codes::table.filter(mfa_recovery_codes::user_id.eq(user_id)))
+                    .execute(conn)?;
+                diesel::delete(user_mfa::table.find(user_id))
+                    .execute(conn)?;
+                Ok(())
+            })
+        })
+        .await?
+    }
+}
@@ -4,4 +4,13 @@ pub mod user;
 pub mod role;
 pub mod pool;
 pub mod error;
-pub mod action;
\ No newline at end of file
+pub mod action;
+pub mod token;
+pub mod login_attempt;
+pub mod mfa;
+pub mod action_token;
+pub mod password_history;
+pub mod api_key;
+pub mod menu;
+pub mod audit;
+pub mod cursor;
\ No newline at end of file
@@ -0,0 +1,76 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use crate::domain::error::RepoError;
+use crate::domain::security::password_history::PasswordHistoryRepo;
+use super::schema::password_history;
+use super::pool::{self, DbConn};
+use std::sync::Arc;
+
+#[derive(Insertable)]
+#[diesel(table_name=password_history)]
+pub struct NewPasswordHistory {
+    pub user_id: i32,
+    pub password_hash: String,
+    pub created_at: Option<NaiveDateTime>,
+}
+
+// impl repo
+pub struct PasswordHistoryDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl PasswordHistoryDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        PasswordHistoryDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordHistoryRepo for PasswordHistoryDieselImpl {
+    async fn add(&self, user_id: i32, password_hash: String, keep: i64) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                let entry = NewPasswordHistory {
+                    user_id,
+                    password_hash,
+                    created_at: Some(chrono::Utc::now().naive_utc()),
+                };
+                diesel::insert_into(password_history::table)
+                    .values(&entry)
+                    .execute(conn)?;
+
+                let expired = password_history::table
+                    .filter(password_history::user_id.eq(user_id))
+                    .order(password_history::id.desc())
+                    .offset(keep.max(0))
+                    .limit(i64::MAX)
+                    .select(password_history::id)
+                    .load::<i32>(conn)?;
+                if !expired.is_empty() {
+                    diesel::delete(password_history::table.filter(password_history::id.eq_any(expired)))
+                        .execute(conn)?;
+                }
+                Ok(())
+            })
+        })
+        .await?
+    }
+    async fn get_recent(&self, user_id: i32, limit: i64) -> Result<Vec<String>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = password_history::table
+                .filter(password_history::user_id.eq(user_id))
+                .order(password_history::id.desc())
+                .limit(limit)
+                .select(password_history::password_hash)
+                .load::<String>(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+}
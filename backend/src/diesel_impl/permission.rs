@@ -1,9 +1,14 @@
 use diesel::prelude::*;
-use futures_util::future::join_all;
+use std::collections::HashMap;
+use common_model::page::{PageData, PageRequest};
+use serde_json::json;
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
 use crate::domain::error::RepoError;
+use crate::domain::permission::policy::Policy;
 use crate::domain::permission::repo::{Permission,PermissionRepo,Action};
 use super::action::ActionDiesel;
-use super::schema::{permissions, role_permissions, actions};
+use super::schema::{permissions, permission_actions, role_permissions, actions};
+use super::cursor::{KeyColumn, Keyset, Paging};
 use super::pool::{self, DbConn};
 use std::sync::Arc;
 
@@ -13,29 +18,82 @@ use std::sync::Arc;
 pub struct PermissionDiesel{
     pub id: i32,
     pub resource: String,
-    pub action: String,//1,2,3,4
-    pub description: Option<String>
+    pub description: Option<String>,
+    pub policy: Option<String>,
 }
 
-impl PermissionDiesel {
-    fn to_action_ids(&self) -> Vec<i32> {
-        self.action
-            .split(',')
-            .filter_map(|s| s.trim().parse::<i32>().ok()) 
-            .collect()
-    }
-}
 
+// a policy that can't be read must not turn into an unconditional grant
+impl TryFrom<PermissionDiesel> for Permission{
+    type Error = RepoError;
 
-impl From<PermissionDiesel> for Permission{
-    fn from(value: PermissionDiesel) -> Self {
-        Permission {
+    fn try_from(value: PermissionDiesel) -> Result<Self, Self::Error> {
+        let policy = value.policy
+            .map(|policy| serde_json::from_str::<Policy>(&policy)
+                .map_err(|e| RepoError{message: format!("Invalid policy in permissions {}: {}", value.id, e)}))
+            .transpose()?;
+        Ok(Permission {
             id: value.id,
             resource: value.resource,
             action: Vec::new(),
             description: value.description.unwrap_or("".to_owned()),
+            policy,
+        })
+    }
+}
+
+fn policy_json(policy: Option<Policy>) -> Result<Option<String>, RepoError> {
+    policy
+        .map(|policy| serde_json::to_string(&policy).map_err(|e| RepoError{message: e.to_string()}))
+        .transpose()
+}
+
+// one row per granted action (or a single row without one), folded back into permissions in query order
+fn group_actions(rows: Vec<(PermissionDiesel, Option<ActionDiesel>)>) -> Result<Vec<Permission>, RepoError> {
+    let mut permissions: Vec<Permission> = Vec::new();
+    let mut positions: HashMap<i32, usize> = HashMap::new();
+    for (permission_diesel, action_diesel) in rows {
+        let position = match positions.get(&permission_diesel.id) {
+            Some(position) => *position,
+            None => {
+                positions.insert(permission_diesel.id, permissions.len());
+                permissions.push(permission_diesel.try_into()?);
+                permissions.len() - 1
+            }
+        };
+        if let Some(action_diesel) = action_diesel {
+            let permission = &mut permissions[position];
+            if !permission.action.iter().any(|action| action.id == action_diesel.id) {
+                permission.action.push(action_diesel.try_into()?);
+            }
         }
     }
+    Ok(permissions)
+}
+
+#[derive(Insertable)]
+#[diesel(table_name=permissions)]
+pub struct NewPermission {
+    pub resource: String,
+    pub description: Option<String>,
+    pub policy: Option<String>,
+}
+
+fn replace_actions(conn: &mut MysqlConnection, permission_id: i32, action_ids: Vec<i32>) -> Result<(), RepoError> {
+    diesel::delete(permission_actions::table.filter(permission_actions::permission_id.eq(permission_id)))
+        .execute(conn)?;
+
+    let rows: Vec<_> = action_ids
+        .into_iter()
+        .map(|action_id| (
+            permission_actions::permission_id.eq(permission_id),
+            permission_actions::action_id.eq(action_id),
+        ))
+        .collect();
+    diesel::insert_into(permission_actions::table)
+        .values(&rows)
+        .execute(conn)?;
+    Ok(())
 }
 
 // impl repo
@@ -48,76 +106,117 @@ impl PermissionDieselImpl {
     pub fn new(pool: Arc<DbConn>)->Self{
         PermissionDieselImpl {pool}
     }
-    async fn get_actions_by_ids(&self, ids: Vec<i32>) -> Result<Vec<Action>, RepoError> {
+}
+
+#[async_trait::async_trait]
+impl PermissionRepo for PermissionDieselImpl {
+    async fn get(&self) -> Result<Vec<Permission>, RepoError> {
         let pool = self.pool.clone();
         pool::run(move || {
             let mut conn = pool.get()?;
 
-            actions::table
-                .filter(actions::id.eq_any(ids))
-                .load::<ActionDiesel>(&mut conn)
-                .map(|actions| actions.into_iter().map(|v| Ok(v.into())).collect())?
+            let rows = permissions::table
+                .left_join(permission_actions::table.on(permission_actions::permission_id.eq(permissions::id)))
+                .left_join(actions::table.on(actions::id.eq(permission_actions::action_id)))
+                .select((PermissionDiesel::as_select(), Option::<ActionDiesel>::as_select()))
+                .order_by(permissions::id)
+                .load::<(PermissionDiesel, Option<ActionDiesel>)>(&mut conn)?;
+
+            group_actions(rows)
         })
         .await?
     }
-    async fn map_to_permission(
-        &self,
-        permission_diesel: PermissionDiesel,
-    ) -> Result<Permission, RepoError> {
-        let action_ids = permission_diesel.to_action_ids();
-        let actions = self.get_actions_by_ids(action_ids).await?;
-        Ok(Permission {
-            id: permission_diesel.id,
-            resource: permission_diesel.resource,
-            action: actions,
-            description: permission_diesel.description.unwrap_or_default(),
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Permission>, RepoError> {
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let keyset = Keyset::new("id", vec![KeyColumn::new::<_, i32>(permissions::id, false)]);
+
+            // the page is cut on permissions alone, the join would multiply rows by their actions
+            let total = permissions::table.count().get_result::<i64>(&mut conn)?;
+            let mut query = permissions::table.select(permissions::id).into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let ids = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<i32>(&mut conn)?;
+            let page = paging.page(ids, total, &keyset, |id| vec![json!(id)]);
+
+            let rows = permissions::table
+                .left_join(permission_actions::table.on(permission_actions::permission_id.eq(permissions::id)))
+                .left_join(actions::table.on(actions::id.eq(permission_actions::action_id)))
+                .filter(permissions::id.eq_any(&page.data))
+                .select((PermissionDiesel::as_select(), Option::<ActionDiesel>::as_select()))
+                .order_by(permissions::id)
+                .load::<(PermissionDiesel, Option<ActionDiesel>)>(&mut conn)?;
+
+            Ok(PageData {
+                page_num: page.page_num,
+                page_size: page.page_size,
+                total: page.total,
+                data: group_actions(rows)?,
+                next_cursor: page.next_cursor,
+                prev_cursor: page.prev_cursor,
+            })
         })
+        .await?
     }
-}
-
-#[async_trait::async_trait]
-impl PermissionRepo for PermissionDieselImpl {
-    async fn get(&self) -> Result<Vec<Permission>, RepoError> {
+    async fn get_by_id(&self, id: i32) -> Result<Permission, RepoError> {
         let pool = self.pool.clone();
-
-        let results = pool::run(move || {
+        pool::run(move || {
             let mut conn = pool.get()?;
-            permissions::table.load::<PermissionDiesel>(&mut conn).map_err(RepoError::from)
+
+            let rows = permissions::table
+                .left_join(permission_actions::table.on(permission_actions::permission_id.eq(permissions::id)))
+                .left_join(actions::table.on(actions::id.eq(permission_actions::action_id)))
+                .filter(permissions::id.eq(id))
+                .select((PermissionDiesel::as_select(), Option::<ActionDiesel>::as_select()))
+                .load::<(PermissionDiesel, Option<ActionDiesel>)>(&mut conn)?;
+
+            group_actions(rows)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| diesel::result::Error::NotFound.into())
         })
-        .await??;
-    
-        let tasks = results.into_iter().map(|permissions_diesel| {
-            let self_clone = self;
-            async move {
-                self_clone.map_to_permission(permissions_diesel).await
-            }
-        });
-    
-        let permissions = join_all(tasks).await;
-        permissions.into_iter().collect()
+        .await?
     }
     async fn get_permissions_by_role_id(&self, role_id: i32)->Result<Vec<Permission>, RepoError>{
         let pool = self.pool.clone();
-        let results=pool::run(move || {
+        pool::run(move || {
             let mut conn = pool.get()?;
 
-            role_permissions::table
+            let rows = role_permissions::table
                 .inner_join(permissions::table.on(role_permissions::permission_id.eq(permissions::id)))
+                .left_join(permission_actions::table.on(permission_actions::permission_id.eq(permissions::id)))
+                .left_join(actions::table.on(actions::id.eq(permission_actions::action_id)))
                 .filter(role_permissions::role_id.eq(role_id))
-                .select(permissions::all_columns)
-                .load::<PermissionDiesel>(&mut conn).map_err(RepoError::from)
+                .select((PermissionDiesel::as_select(), Option::<ActionDiesel>::as_select()))
+                .order_by(permissions::id)
+                .load::<(PermissionDiesel, Option<ActionDiesel>)>(&mut conn)?;
+
+            group_actions(rows)
         })
-        .await??;
+        .await?
+    }
+    async fn get_role_ids(&self, id: i32) -> Result<Vec<i32>, RepoError> {
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
 
-        let tasks = results.into_iter().map(|permissions_diesel| {
-            let self_clone = self;
-            async move {
-                self_clone.map_to_permission(permissions_diesel).await
-            }
-        });
-    
-        let permissions = join_all(tasks).await;
-        permissions.into_iter().collect()
+            let result = role_permissions::table
+                .filter(role_permissions::permission_id.eq(id))
+                .select(role_permissions::role_id)
+                .distinct()
+                .load::<i32>(&mut conn)?;
+            Ok(result)
+        })
+        .await?
     }
     async fn get_actions_by_ids(&self, ids: Vec<i32>)-> Result<Vec<Action>, RepoError>{
         let pool = self.pool.clone();
@@ -128,8 +227,78 @@ impl PermissionRepo for PermissionDieselImpl {
                 .filter(actions::id.eq_any(ids))
                 .load::<ActionDiesel>(&mut conn)?;
 
-            result.into_iter().map(|action| Ok(action.into())).collect()
+            result.into_iter().map(Action::try_from).collect()
         })
         .await?
     }
-}
\ No newline at end of file
+    async fn create(&self, resource: String, description: String, action_ids: Vec<i32>, policy: Option<Policy>) -> Result<Permission, RepoError> {
+        let policy = policy_json(policy)?;
+        let pool = self.pool.clone();
+        let inserted_id = pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                let new_permission = NewPermission { resource, description: Some(description), policy };
+
+                let result = diesel::insert_into(permissions::table)
+                    .values(&new_permission)
+                    .execute(conn)?;
+                if result == 0 {
+                    return Err(RepoError{message:"Can't inserted".to_string()});
+                }
+                let id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+                    "LAST_INSERT_ID()",
+                ))
+                .get_result::<i32>(conn)?;
+
+                replace_actions(conn, id, action_ids)?;
+                Ok(id)
+            })
+        })
+        .await??;
+
+        self.get_by_id(inserted_id).await
+    }
+    async fn update(&self, id: i32, resource: String, description: String, action_ids: Vec<i32>, policy: Option<Policy>) -> Result<Permission, RepoError> {
+        let policy = policy_json(policy)?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                diesel::update(permissions::table.find(id))
+                    .set((
+                        permissions::resource.eq(resource),
+                        permissions::description.eq(description),
+                        permissions::policy.eq(policy),
+                    ))
+                    .execute(conn)?;
+
+                replace_actions(conn, id, action_ids)
+            })
+        })
+        .await??;
+
+        self.get_by_id(id).await
+    }
+    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError> {
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                diesel::delete(role_permissions::table.filter(role_permissions::permission_id.eq(id)))
+                    .execute(conn)?;
+
+                let result = diesel::delete(permissions::table.find(id))
+                    .execute(conn)?;
+
+                if result==0{
+                    return Err(RepoError{message:"Can't Delete".to_string()});
+                }
+                Ok(id)
+            })
+        })
+        .await?
+    }
+}
@@ -1,9 +1,48 @@
+use diesel::dsl::{exists, not};
 use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use common_model::page::{PageData, PageRequest};
+use serde_json::json;
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
 use crate::domain::error::RepoError;
-use crate::domain::role::repo::{Role,RoleRepo};
-use super::schema::{roles, user_roles};
+use crate::domain::role::repo::{Role,RoleDelete,RoleGrant,RoleMember,RoleRepo};
+use super::schema::{role_permissions, roles, user_roles, users};
+use super::cursor::{KeyColumn, Keyset, Paging};
 use super::pool::{self, DbConn};
 use std::sync::Arc;
+use chrono::NaiveDateTime;
+
+fn count_members(conn: &mut MysqlConnection, id: i32, now: NaiveDateTime) -> Result<i64, RepoError> {
+    let count = user_roles::table
+        .filter(user_roles::role_id.eq(id))
+        .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)))
+        .count()
+        .get_result::<i64>(conn)?;
+    Ok(count)
+}
+
+// upsert of one grant, the (user_id, role_id) pair is unique
+fn grant_role(conn: &mut MysqlConnection, user_id: i32, role_id: i32, expires_at: Option<NaiveDateTime>) -> Result<(), RepoError> {
+    let updated = diesel::update(
+        user_roles::table
+            .filter(user_roles::user_id.eq(user_id))
+            .filter(user_roles::role_id.eq(role_id)),
+    )
+    .set(user_roles::expires_at.eq(expires_at))
+    .execute(conn)?;
+    if updated > 0 {
+        return Ok(());
+    }
+
+    let new_grant = NewUserRole { user_id, role_id, granted_at: Some(chrono::Utc::now().naive_utc()), expires_at };
+    let result = diesel::insert_into(user_roles::table)
+        .values(&new_grant)
+        .execute(conn)?;
+    if result == 0 {
+        return Err(RepoError{message:"Can't inserted".to_string()});
+    }
+    Ok(())
+}
 
 #[derive(Debug,Queryable,Selectable)]
 #[diesel(table_name=roles)]
@@ -11,7 +50,9 @@ use std::sync::Arc;
 pub struct RoleDiesel{
     pub id: i32,
     pub name: String,
-    pub description: Option<String>
+    pub description: Option<String>,
+    pub require_mfa: bool,
+    pub parent_role_id: Option<i32>,
 }
 
 impl From<RoleDiesel> for Role {
@@ -20,6 +61,8 @@ impl From<RoleDiesel> for Role {
             id: value.id,
             name: value.name,
             description: value.description.unwrap_or_else(|| "".to_string()),
+            require_mfa: value.require_mfa,
+            parent_role_id: value.parent_role_id,
         }
     }
 }
@@ -28,16 +71,28 @@ impl From<Role> for RoleDiesel {
         RoleDiesel { 
             id: value.id, 
             name: value.name, 
-            description: Some(value.description)
+            description: Some(value.description),
+            require_mfa: value.require_mfa,
+            parent_role_id: value.parent_role_id,
         }
     }
 }
 
+#[derive(Insertable)]
+#[diesel(table_name=user_roles)]
+pub struct NewUserRole {
+    pub user_id: i32,
+    pub role_id: i32,
+    pub granted_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
 #[derive(Insertable)]
 #[diesel(table_name=roles)]
 pub struct NewRole {
     pub name: String,
-    pub description: Option<String>
+    pub description: Option<String>,
+    pub parent_role_id: Option<i32>,
 }
 
 // impl repo
@@ -64,6 +119,32 @@ impl RoleRepo for RoleDieselImpl {
         })
         .await?
     }
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Role>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let keyset = Keyset::new("id", vec![KeyColumn::new::<_, i32>(roles::id, false)]);
+
+            let total = roles::table.count().get_result::<i64>(&mut conn)?;
+            let mut query = roles::table.into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<RoleDiesel>(&mut conn)?;
+
+            Ok(paging
+                .page(result, total, &keyset, |role| vec![json!(role.id)])
+                .map(|role| role.into()))
+        })
+        .await?
+    }
     async fn get_by_id(&self, id: i32) -> Result<Role, RepoError>{
         let pool = self.pool.clone();
         pool::run(move || {
@@ -76,12 +157,25 @@ impl RoleRepo for RoleDieselImpl {
         })
         .await?
     }
-    async fn create(&self, name: String, description: String) -> Result<Role, RepoError>{
+    async fn get_by_name(&self, name: String) -> Result<Option<Role>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = roles::table
+                .filter(roles::name.eq(name))
+                .first::<RoleDiesel>(&mut conn)
+                .optional()?;
+
+            Ok(result.map(|role| role.into()))
+        })
+        .await?
+    }
+    async fn create(&self, name: String, description: String, parent_role_id: Option<i32>) -> Result<Role, RepoError>{
         let pool = self.pool.clone();
         let inserted_id = pool::run(move || {
             let mut conn = pool.get()?;
 
-            let new_role = NewRole { name, description: Some(description) };
+            let new_role = NewRole { name, description: Some(description), parent_role_id };
 
             let result = diesel::insert_into(roles::table)
                 .values(&new_role)
@@ -100,7 +194,7 @@ impl RoleRepo for RoleDieselImpl {
 
         self.get_by_id(inserted_id).await
     }
-    async fn update(&self, id: i32, name: String, description: String) -> Result<Role, RepoError>{
+    async fn update(&self, id: i32, name: String, description: String, parent_role_id: Option<i32>) -> Result<Role, RepoError>{
         let pool = self.pool.clone();
         pool::run(move || {
             let mut conn = pool.get()?;
@@ -109,6 +203,7 @@ impl RoleRepo for RoleDieselImpl {
                 .set((
                     roles::name.eq(name),
                     roles::description.eq(description),
+                    roles::parent_role_id.eq(parent_role_id),
                 ))
                 .execute(&mut conn)?;
 
@@ -123,18 +218,89 @@ impl RoleRepo for RoleDieselImpl {
         })
         .await?
     }
-    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>{
+    async fn delete_by_id(&self, id: i32) -> Result<RoleDelete, RepoError>{
         let pool = self.pool.clone();
         pool::run(move || {
             let mut conn = pool.get()?;
 
-            let result = diesel::delete(roles::table.find(id.clone()))
-                .execute(&mut conn)?;
+            conn.transaction::<_, RepoError, _>(|conn| {
+                // checked by the delete itself, a grant made meanwhile can't leave a member without its role
+                let now = chrono::Utc::now().naive_utc();
+                let members = user_roles::table
+                    .filter(user_roles::role_id.eq(id))
+                    .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)));
+                let result = diesel::delete(roles::table.find(id).filter(not(exists(members))))
+                    .execute(conn);
 
-            if result==0{
-                return Err(RepoError{message:"Can't Delete".to_string()});
-            }
-            Ok(id)
+                match result {
+                    Ok(0) => {
+                        if roles::table.find(id).count().get_result::<i64>(conn)? == 0 {
+                            return Ok(RoleDelete::NotFound);
+                        }
+                        Ok(RoleDelete::Assigned(count_members(conn, id, now)?))
+                    }
+                    Ok(_) => {
+                        diesel::delete(role_permissions::table.filter(role_permissions::role_id.eq(id)))
+                            .execute(conn)?;
+                        diesel::delete(user_roles::table.filter(user_roles::role_id.eq(id)))
+                            .execute(conn)?;
+                        Ok(RoleDelete::Deleted)
+                    }
+                    // the parent_role_id key of a child role
+                    Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => {
+                        let children = roles::table
+                            .filter(roles::parent_role_id.eq(id))
+                            .count()
+                            .get_result::<i64>(conn)?;
+                        Ok(RoleDelete::Inherited(children))
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            })
+        })
+        .await?
+    }
+    async fn add_permission(&self, id: i32, permission_id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                let assigned = role_permissions::table
+                    .filter(role_permissions::role_id.eq(id))
+                    .filter(role_permissions::permission_id.eq(permission_id))
+                    .count()
+                    .get_result::<i64>(conn)?;
+                if assigned > 0 {
+                    return Ok(());
+                }
+
+                let result = diesel::insert_into(role_permissions::table)
+                    .values((
+                        role_permissions::role_id.eq(id),
+                        role_permissions::permission_id.eq(permission_id),
+                    ))
+                    .execute(conn)?;
+                if result == 0 {
+                    return Err(RepoError{message:"Can't inserted".to_string()});
+                }
+                Ok(())
+            })
+        })
+        .await?
+    }
+    async fn remove_permission(&self, id: i32, permission_id: i32) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::delete(
+                role_permissions::table
+                    .filter(role_permissions::role_id.eq(id))
+                    .filter(role_permissions::permission_id.eq(permission_id)),
+            )
+            .execute(&mut conn)?;
+            Ok(result)
         })
         .await?
     }
@@ -142,13 +308,167 @@ impl RoleRepo for RoleDieselImpl {
         let pool = self.pool.clone();
         pool::run(move || {
             let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
             let result= roles::table.inner_join(user_roles::table.on(user_roles::role_id.eq(roles::id)))
                             .filter(user_roles::user_id.eq(user_id))
-                            .select((roles::id, roles::name, roles::description))
+                            .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)))
+                            .select(RoleDiesel::as_select())
                             .load::<RoleDiesel>(&mut conn)?;
             result.into_iter().map(|role| Ok(role.into())).collect()
         })
         .await?
 
     }
+    async fn get_grants_by_user_id(&self, user_id: i32) -> Result<Vec<RoleGrant>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
+            let result= roles::table.inner_join(user_roles::table.on(user_roles::role_id.eq(roles::id)))
+                            .filter(user_roles::user_id.eq(user_id))
+                            .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)))
+                            .select((RoleDiesel::as_select(), user_roles::granted_at, user_roles::expires_at))
+                            .load::<(RoleDiesel, Option<NaiveDateTime>, Option<NaiveDateTime>)>(&mut conn)?;
+            Ok(result
+                .into_iter()
+                .map(|(role, granted_at, expires_at)| RoleGrant { role: role.into(), granted_at, expires_at })
+                .collect())
+        })
+        .await?
+    }
+    async fn get_grants_page(&self, user_id: i32, page: PageRequest) -> Result<PageData<RoleGrant>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
+            let keyset = Keyset::new("id", vec![KeyColumn::new::<_, i32>(roles::id, false)]);
+            let grants = || roles::table.inner_join(user_roles::table.on(user_roles::role_id.eq(roles::id)))
+                .filter(user_roles::user_id.eq(user_id))
+                .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)));
+
+            let total = grants().count().get_result::<i64>(&mut conn)?;
+            let mut query = grants().into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .select((RoleDiesel::as_select(), user_roles::granted_at, user_roles::expires_at))
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<(RoleDiesel, Option<NaiveDateTime>, Option<NaiveDateTime>)>(&mut conn)?
+                .into_iter()
+                .map(|(role, granted_at, expires_at)| RoleGrant { role: role.into(), granted_at, expires_at })
+                .collect();
+
+            Ok(paging.page(result, total, &keyset, |grant: &RoleGrant| vec![json!(grant.role.id)]))
+        })
+        .await?
+    }
+    async fn get_members(&self, id: i32, page: PageRequest) -> Result<PageData<RoleMember>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "username")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let now = chrono::Utc::now().naive_utc();
+            let keyset = Keyset::new("username", vec![
+                KeyColumn::new::<_, String>(users::username, false),
+                KeyColumn::new::<_, i32>(users::id, false),
+            ]);
+            let members = || users::table.inner_join(user_roles::table.on(user_roles::user_id.eq(users::id)))
+                .filter(user_roles::role_id.eq(id))
+                .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)));
+
+            let total = members().count().get_result::<i64>(&mut conn)?;
+            let mut query = members().into_boxed();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .select((users::id, users::username, users::email, user_roles::granted_at, user_roles::expires_at))
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<(i32, String, String, Option<NaiveDateTime>, Option<NaiveDateTime>)>(&mut conn)?
+                .into_iter()
+                .map(|(user_id, username, email, granted_at, expires_at)| RoleMember { user_id, username, email, granted_at, expires_at })
+                .collect();
+
+            Ok(paging.page(result, total, &keyset, |member| vec![json!(member.username), json!(member.user_id)]))
+        })
+        .await?
+    }
+    async fn get_member_ids(&self, ids: Vec<i32>) -> Result<Vec<i32>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = user_roles::table
+                .filter(user_roles::role_id.eq_any(ids))
+                .select(user_roles::user_id)
+                .distinct()
+                .load::<i32>(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+    async fn grant(&self, user_id: i32, id: i32, expires_at: Option<NaiveDateTime>) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| grant_role(conn, user_id, id, expires_at))
+        })
+        .await?
+    }
+    async fn grant_to_users(&self, user_ids: Vec<i32>, id: i32, expires_at: Option<NaiveDateTime>) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                for user_id in user_ids {
+                    grant_role(conn, user_id, id, expires_at)?;
+                }
+                Ok(())
+            })
+        })
+        .await?
+    }
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::delete(
+                user_roles::table
+                    .filter(user_roles::user_id.eq(user_id))
+                    .filter(user_roles::role_id.eq(id)),
+            )
+            .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+    async fn set_require_mfa(&self, id: i32, require_mfa: bool) -> Result<Role, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(roles::table.find(id))
+                .set(roles::require_mfa.eq(require_mfa))
+                .execute(&mut conn)?;
+
+            let role_update = roles::table
+                .find(id)
+                .first::<RoleDiesel>(&mut conn)?;
+
+            Ok(role_update.into())
+        })
+        .await?
+    }
 }
\ No newline at end of file
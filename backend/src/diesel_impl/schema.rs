@@ -10,13 +10,116 @@ diesel::table! {
 }
 
 diesel::table! {
-    permissions (id) {
+    api_keys (id) {
+        id -> Integer,
+        user_id -> Integer,
+        #[max_length = 100]
+        name -> Varchar,
+        #[max_length = 20]
+        prefix -> Varchar,
+        #[max_length = 64]
+        key_hash -> Varchar,
+        scopes -> Text,
+        created_at -> Nullable<Datetime>,
+        last_used_at -> Nullable<Datetime>,
+        expires_at -> Nullable<Datetime>,
+        revoked -> Bool,
+    }
+}
+
+diesel::table! {
+    audit_logs (id) {
         id -> Integer,
+        user_id -> Integer,
+        api_key_id -> Nullable<Integer>,
         #[max_length = 50]
         resource -> Varchar,
         #[max_length = 50]
         action -> Varchar,
+        #[max_length = 100]
+        resource_id -> Nullable<Varchar>,
+        allowed -> Bool,
+        reason -> Text,
+        #[max_length = 10]
+        method -> Varchar,
+        #[max_length = 255]
+        path -> Varchar,
+        #[max_length = 45]
+        ip_address -> Nullable<Varchar>,
+        created_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    login_attempts (id) {
+        id -> Integer,
+        user_id -> Nullable<Integer>,
+        #[max_length = 255]
+        identifier -> Varchar,
+        #[max_length = 45]
+        ip_address -> Nullable<Varchar>,
+        succeeded -> Bool,
+        attempted_at -> Datetime,
+    }
+}
+
+diesel::table! {
+    menu_items (id) {
+        id -> Integer,
+        parent_id -> Nullable<Integer>,
+        #[max_length = 50]
+        code -> Varchar,
+        #[max_length = 100]
+        label_en_us -> Varchar,
+        #[max_length = 100]
+        label_zh_cn -> Varchar,
+        #[max_length = 50]
+        icon -> Nullable<Varchar>,
+        #[max_length = 255]
+        path -> Varchar,
+        #[max_length = 50]
+        resource -> Nullable<Varchar>,
+        #[max_length = 50]
+        action -> Nullable<Varchar>,
+        sort_order -> Integer,
+    }
+}
+
+diesel::table! {
+    mfa_recovery_codes (id) {
+        id -> Integer,
+        user_id -> Integer,
+        #[max_length = 255]
+        code_hash -> Varchar,
+        used_at -> Nullable<Datetime>,
+    }
+}
+
+diesel::table! {
+    password_history (id) {
+        id -> Integer,
+        user_id -> Integer,
+        #[max_length = 255]
+        password_hash -> Varchar,
+        created_at -> Nullable<Datetime>,
+    }
+}
+
+diesel::table! {
+    permission_actions (id) {
+        id -> Integer,
+        permission_id -> Integer,
+        action_id -> Integer,
+    }
+}
+
+diesel::table! {
+    permissions (id) {
+        id -> Integer,
+        #[max_length = 50]
+        resource -> Varchar,
         description -> Nullable<Text>,
+        policy -> Nullable<Text>,
     }
 }
 
@@ -34,6 +137,8 @@ diesel::table! {
         #[max_length = 50]
         name -> Varchar,
         description -> Nullable<Text>,
+        require_mfa -> Bool,
+        parent_role_id -> Nullable<Integer>,
     }
 }
 
@@ -52,6 +157,36 @@ diesel::table! {
         last_used_at -> Nullable<Datetime>,
         expires_at -> Datetime,
         revoked -> Nullable<Bool>,
+        #[max_length = 20]
+        token_type -> Varchar,
+        #[max_length = 36]
+        family_id -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    user_action_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        #[max_length = 30]
+        purpose -> Varchar,
+        created_at -> Nullable<Datetime>,
+        expires_at -> Datetime,
+        used_at -> Nullable<Datetime>,
+    }
+}
+
+diesel::table! {
+    user_mfa (user_id) {
+        user_id -> Integer,
+        #[max_length = 64]
+        secret -> Varchar,
+        enabled -> Bool,
+        created_at -> Nullable<Datetime>,
+        confirmed_at -> Nullable<Datetime>,
+        last_used_step -> Nullable<Bigint>,
     }
 }
 
@@ -60,6 +195,8 @@ diesel::table! {
         id -> Integer,
         user_id -> Integer,
         role_id -> Integer,
+        granted_at -> Nullable<Datetime>,
+        expires_at -> Nullable<Datetime>,
     }
 }
 
@@ -75,15 +212,33 @@ diesel::table! {
         email -> Varchar,
         is_active -> Nullable<Bool>,
         created_at -> Nullable<Datetime>,
+        failed_login_count -> Integer,
+        locked_until -> Nullable<Datetime>,
+        email_verified -> Bool,
+        is_service_account -> Bool,
+        permissions_version -> Integer,
+        #[max_length = 100]
+        department -> Nullable<Varchar>,
+        version -> Integer,
+        updated_at -> Nullable<Datetime>,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
     actions,
+    api_keys,
+    audit_logs,
+    login_attempts,
+    menu_items,
+    mfa_recovery_codes,
+    password_history,
+    permission_actions,
     permissions,
     role_permissions,
     roles,
     tokens,
+    user_action_tokens,
+    user_mfa,
     user_roles,
     users,
 );
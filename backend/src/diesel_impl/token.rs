@@ -0,0 +1,298 @@
+use diesel::prelude::*;
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde_json::json;
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
+use crate::domain::error::RepoError;
+use crate::domain::security::token::{NewUserToken, Session, TokenRepo, TokenType, UserToken};
+use diesel::dsl::{count, max, min};
+use super::schema::tokens;
+use super::cursor::{KeyColumn, Keyset, Paging};
+use super::pool::{self, DbConn};
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[derive(Debug,Queryable,Selectable)]
+#[diesel(table_name=tokens)]
+#[diesel(check_for_backend(diesel::mysql::Mysql))]
+pub struct TokenDiesel{
+    pub id: i32,
+    pub user_id: i32,
+    pub token: String,
+    pub device_id: Option<String>,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub revoked: Option<bool>,
+    pub token_type: String,
+    pub family_id: Option<String>,
+}
+
+impl From<TokenDiesel> for UserToken {
+    fn from(value: TokenDiesel) -> Self {
+        UserToken {
+            id: value.id,
+            user_id: value.user_id,
+            token: value.token,
+            // unknown types are treated as access tokens, which can never be used to refresh
+            token_type: TokenType::from_str(&value.token_type).unwrap_or(TokenType::Access),
+            family_id: value.family_id.unwrap_or_default(),
+            device_id: value.device_id,
+            device_info: value.device_info,
+            ip_address: value.ip_address,
+            created_at: value.created_at.unwrap_or_default(),
+            last_used_at: value.last_used_at,
+            expires_at: value.expires_at,
+            revoked: value.revoked.unwrap_or(false),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name=tokens)]
+pub struct NewToken {
+    pub user_id: i32,
+    pub token: String,
+    pub device_id: Option<String>,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub revoked: Option<bool>,
+    pub token_type: String,
+    pub family_id: Option<String>,
+}
+
+// impl repo
+pub struct TokenDieselImpl{
+    pool: Arc<DbConn>,
+}
+
+impl TokenDieselImpl {
+    pub fn new(pool: Arc<DbConn>)-> Self{
+        TokenDieselImpl { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenRepo for TokenDieselImpl {
+    async fn create(&self, token: NewUserToken) -> Result<UserToken, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let token_hash = token.token_hash;
+            let new_token = NewToken {
+                user_id: token.user_id,
+                token: token_hash.clone(),
+                device_id: token.client.device_id,
+                device_info: token.client.device_info,
+                ip_address: token.client.ip_address,
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                expires_at: token.expires_at,
+                revoked: Some(false),
+                token_type: token.token_type.as_str().to_string(),
+                family_id: Some(token.family_id),
+            };
+
+            let result = diesel::insert_into(tokens::table)
+                .values(&new_token)
+                .execute(&mut conn)?;
+            if result == 0 {
+                return Err(RepoError{message:"Can't inserted".to_string()});
+            }
+            let token = tokens::table
+                .filter(tokens::token.eq(token_hash))
+                .first::<TokenDiesel>(&mut conn)?;
+
+            Ok(token.into())
+        })
+        .await?
+    }
+    async fn get_by_token(&self, token_hash: String) -> Result<UserToken, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let result = tokens::table
+                .filter(tokens::token.eq(token_hash))
+                .first::<TokenDiesel>(&mut conn)?;
+
+            Ok(result.into())
+        })
+        .await?
+    }
+    async fn touch(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(tokens::table.find(id))
+                .set(tokens::last_used_at.eq(chrono::Utc::now().naive_utc()))
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn revoke(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table.find(id))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+
+            if result == 0 {
+                return Err(RepoError{message:"Can't revoked".to_string()});
+            }
+            Ok(())
+        })
+        .await?
+    }
+    async fn revoke_if_active(&self, id: i32) -> Result<bool, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table.find(id))
+                .filter(tokens::revoked.eq(false))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result == 1)
+        })
+        .await?
+    }
+    async fn revoke_family(&self, family_id: String) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table)
+                .filter(tokens::family_id.eq(family_id))
+                .filter(tokens::revoked.eq(false))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+    async fn revoke_by_user_id(&self, user_id: i32) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table)
+                .filter(tokens::user_id.eq(user_id))
+                .filter(tokens::revoked.eq(false))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+    async fn get_sessions_by_user_id(&self, user_id: i32, page: PageRequest) -> Result<PageData<Session>, RepoError>{
+        let paging = Paging::new(page.page_num, page.page_size.unwrap_or(DEFAULT_PAGE_SIZE), page.cursor.as_deref(), "-id")?;
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+            let keyset = Keyset::new("-id", vec![KeyColumn::new::<_, i32>(tokens::id, true)]);
+
+            // the live refresh token of each family carries the latest device and expiry
+            let live = || tokens::table
+                .filter(tokens::user_id.eq(user_id))
+                .filter(tokens::token_type.eq(TokenType::Refresh.as_str()))
+                .filter(tokens::revoked.eq(false))
+                .filter(tokens::expires_at.gt(chrono::Utc::now().naive_utc()))
+                .into_boxed();
+
+            let total = live()
+                .select(count(tokens::family_id).aggregate_distinct())
+                .get_result::<i64>(&mut conn)?;
+            let mut query = live();
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let active = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<TokenDiesel>(&mut conn)?;
+            let page = paging.page(active, total, &keyset, |token| vec![json!(token.id)]);
+
+            let family_ids: Vec<String> = page.data.iter().filter_map(|t| t.family_id.clone()).collect();
+            // first login and last activity are spread over all rows of the family
+            let stats = tokens::table
+                .filter(tokens::user_id.eq(user_id))
+                .filter(tokens::family_id.eq_any(&family_ids))
+                .group_by(tokens::family_id)
+                .select((tokens::family_id, min(tokens::created_at), max(tokens::last_used_at)))
+                .load::<(Option<String>, Option<NaiveDateTime>, Option<NaiveDateTime>)>(&mut conn)?;
+
+            let mut sessions: Vec<Session> = Vec::new();
+            for token in page.data {
+                let Some(family_id) = token.family_id else { continue };
+                if sessions.iter().any(|s| s.id == family_id) {
+                    continue;
+                }
+                let (created_at, last_used_at) = stats
+                    .iter()
+                    .find(|(id, _, _)| id.as_deref() == Some(family_id.as_str()))
+                    .map(|(_, created_at, last_used_at)| (*created_at, *last_used_at))
+                    .unwrap_or((token.created_at, token.last_used_at));
+                sessions.push(Session {
+                    id: family_id,
+                    device_id: token.device_id,
+                    device_info: token.device_info,
+                    ip_address: token.ip_address,
+                    created_at: created_at.unwrap_or_default(),
+                    last_used_at,
+                    expires_at: token.expires_at,
+                    current: false,
+                });
+            }
+            Ok(PageData {
+                page_num: page.page_num,
+                page_size: page.page_size,
+                total: page.total,
+                data: sessions,
+                next_cursor: page.next_cursor,
+                prev_cursor: page.prev_cursor,
+            })
+        })
+        .await?
+    }
+    async fn revoke_session(&self, user_id: i32, family_id: String) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table)
+                .filter(tokens::user_id.eq(user_id))
+                .filter(tokens::family_id.eq(family_id))
+                .filter(tokens::revoked.eq(false))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+    async fn revoke_other_sessions(&self, user_id: i32, keep: String) -> Result<usize, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(tokens::table)
+                .filter(tokens::user_id.eq(user_id))
+                .filter(tokens::family_id.ne(keep))
+                .filter(tokens::revoked.eq(false))
+                .set(tokens::revoked.eq(true))
+                .execute(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
+}
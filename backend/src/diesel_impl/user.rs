@@ -1,9 +1,15 @@
+use common_model::page::PageData;
 use common_model::user::{CreateUserRequest, FilterUserRequest};
 use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
 use crate::domain::error::RepoError;
-use crate::domain::user::repo::{User, UserRepo};
+use crate::domain::user::repo::{parse_user_sort, user_sort_key, NewAccount, User, UserCreate, UserPatch, UserRepo, UserSort, UserSortField, UserUpdate};
+use crate::domain::cursor::DEFAULT_PAGE_SIZE;
+use serde_json::{json, Value};
 
-use super::schema::users;
+use super::schema::{user_roles, users};
+use super::cursor::{KeyColumn, Keyset, Paging};
+use super::role::NewUserRole;
 use super::pool::{self, DbConn};
 use std::sync::Arc;
 use chrono::NaiveDateTime;
@@ -18,7 +24,15 @@ pub struct UserDiesel{
     pub password_hash: String,
     pub email: String,
     pub is_active: Option<bool>,
-    pub created_at: Option<NaiveDateTime>
+    pub created_at: Option<NaiveDateTime>,
+    pub failed_login_count: i32,
+    pub locked_until: Option<NaiveDateTime>,
+    pub email_verified: bool,
+    pub is_service_account: bool,
+    pub permissions_version: i32,
+    pub department: Option<String>,
+    pub version: i32,
+    pub updated_at: Option<NaiveDateTime>,
 }
 
 impl Into<User> for UserDiesel {
@@ -30,7 +44,15 @@ impl Into<User> for UserDiesel {
             password_hash: self.password_hash, 
             email: self.email, 
             is_active: self.is_active.unwrap_or(false), 
-            created_at: self.created_at.unwrap_or(NaiveDateTime::default()) 
+            created_at: self.created_at.unwrap_or(NaiveDateTime::default()),
+            failed_login_count: self.failed_login_count,
+            locked_until: self.locked_until,
+            email_verified: self.email_verified,
+            is_service_account: self.is_service_account,
+            permissions_version: self.permissions_version,
+            department: self.department,
+            version: self.version,
+            updated_at: self.updated_at,
         }
     }
 }
@@ -45,6 +67,14 @@ impl From<User> for UserDiesel {
             email: value.email,
             is_active: Some(value.is_active),
             created_at: Some(value.created_at),
+            failed_login_count: value.failed_login_count,
+            locked_until: value.locked_until,
+            email_verified: value.email_verified,
+            is_service_account: value.is_service_account,
+            permissions_version: value.permissions_version,
+            department: value.department,
+            version: value.version,
+            updated_at: value.updated_at,
         }
     }
 }
@@ -58,8 +88,145 @@ pub struct NewUser {
     pub email: String,
     pub is_active: Option<bool>,
     pub created_at: Option<chrono::NaiveDateTime>,
+    pub is_service_account: bool,
+    pub department: Option<String>,
 }
 
+// only the Some fields are written, Some(None) writes NULL
+#[derive(AsChangeset)]
+#[diesel(table_name=users)]
+pub struct UserChangeset {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub employee_id: Option<Option<i32>>,
+    pub is_active: Option<Option<bool>>,
+    pub department: Option<Option<String>>,
+}
+
+impl From<UserPatch> for UserChangeset {
+    fn from(value: UserPatch) -> Self {
+        UserChangeset {
+            username: value.username,
+            email: value.email,
+            email_verified: value.email_verified,
+            employee_id: value.employee_id,
+            is_active: value.is_active.map(Some),
+            department: value.department,
+        }
+    }
+}
+
+type BoxedUsers = users::BoxedQuery<'static, diesel::mysql::Mysql>;
+
+// % and _ typed by the user are matched literally
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+// the WHERE part of the user list, shared by the page and its total
+fn filtered(filter: &FilterUserRequest) -> BoxedUsers {
+    let mut query = users::table.into_boxed();
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|search| !search.is_empty()) {
+        let pattern = format!("%{}%", escape_like(search));
+        query = query.filter(users::username.like(pattern.clone()).or(users::email.like(pattern)));
+    }
+    if let Some(is_active) = filter.is_active {
+        // a NULL is_active reads as inactive, see User
+        query = if is_active {
+            query.filter(users::is_active.eq(true))
+        } else {
+            query.filter(users::is_active.eq(false).or(users::is_active.is_null()))
+        };
+    }
+    if let Some(role_id) = filter.role_id {
+        let now = chrono::Utc::now().naive_utc();
+        query = query.filter(users::id.eq_any(
+            user_roles::table
+                .filter(user_roles::role_id.eq(role_id))
+                .filter(user_roles::expires_at.is_null().or(user_roles::expires_at.gt(now)))
+                .select(user_roles::user_id),
+        ));
+    }
+    if let Some(employee_id) = filter.employee_id {
+        query = query.filter(users::employee_id.eq(employee_id));
+    }
+    if let Some(created_from) = filter.created_from {
+        query = query.filter(users::created_at.ge(created_from));
+    }
+    if let Some(created_to) = filter.created_to {
+        query = query.filter(users::created_at.le(created_to));
+    }
+    query
+}
+
+// "username" or "email" when `error` comes from their unique keys, in case a check raced with
+// another write. MySQL names the key at the end of the message, "... for key 'users.email'" (or
+// 'email' before 8.0), the duplicate value before it may contain anything
+fn duplicate_field(error: &diesel::result::Error) -> Option<&'static str> {
+    match error {
+        diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+            let key = info.message().rsplit_once("for key ")
+                .map(|(_, key)| key.trim().trim_matches('\''))
+                .unwrap_or_default();
+            let column = key.rsplit('.').next().unwrap_or_default();
+            Some(if column == "email" { "email" } else { "username" })
+        }
+        _ => None,
+    }
+}
+
+// inserts `new_user` and grants it `role_id`, returns the new id. Call inside a transaction
+fn insert_user(conn: &mut MysqlConnection, new_user: &NewUser, role_id: Option<i32>) -> Result<i32, diesel::result::Error> {
+    diesel::insert_into(users::table)
+        .values(new_user)
+        .execute(conn)?;
+    let id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+        "LAST_INSERT_ID()",
+    ))
+    .get_result::<i32>(conn)?;
+
+    if let Some(role_id) = role_id {
+        let new_grant = NewUserRole { user_id: id, role_id, granted_at: new_user.created_at, expires_at: None };
+        diesel::insert_into(user_roles::table)
+            .values(&new_grant)
+            .execute(conn)?;
+    }
+    Ok(id)
+}
+
+// id breaks ties last, so pages don't overlap
+fn keyset(sort: &[UserSort]) -> Keyset<users::table> {
+    let mut columns = sort.iter()
+        .map(|key| match key.field {
+            UserSortField::Id => KeyColumn::new::<_, i32>(users::id, key.descending),
+            UserSortField::Username => KeyColumn::new::<_, String>(users::username, key.descending),
+            UserSortField::Email => KeyColumn::new::<_, String>(users::email, key.descending),
+            UserSortField::EmployeeId => KeyColumn::order_only(users::employee_id, key.descending),
+            UserSortField::IsActive => KeyColumn::order_only(users::is_active, key.descending),
+            UserSortField::CreatedAt => KeyColumn::order_only(users::created_at, key.descending),
+        })
+        .collect::<Vec<_>>();
+    columns.push(KeyColumn::new::<_, i32>(users::id, false));
+
+    Keyset::new(user_sort_key(sort), columns)
+}
+
+// cursor values of a row, in the order of keyset()
+fn keys(user: &UserDiesel, sort: &[UserSort]) -> Vec<Value> {
+    let mut keys = sort.iter()
+        .map(|key| match key.field {
+            UserSortField::Id => json!(user.id),
+            UserSortField::Username => json!(user.username),
+            UserSortField::Email => json!(user.email),
+            UserSortField::EmployeeId => json!(user.employee_id),
+            UserSortField::IsActive => json!(user.is_active),
+            UserSortField::CreatedAt => json!(user.created_at),
+        })
+        .collect::<Vec<_>>();
+    keys.push(json!(user.id));
+    keys
+}
 
 // impl repo
 
@@ -75,23 +242,36 @@ impl UserDieselImpl {
 
 #[async_trait::async_trait]
 impl UserRepo for UserDieselImpl {
-    async fn get(&self, filter: FilterUserRequest) -> Result<Vec<User>, RepoError>{
+    async fn get(&self, filter: FilterUserRequest) -> Result<PageData<User>, RepoError>{
+        let sort = parse_user_sort(filter.sort.as_deref().unwrap_or_default())
+            .map_err(|message| RepoError{message})?;
+        let paging = Paging::new(filter.page_num, filter.page_size.unwrap_or(DEFAULT_PAGE_SIZE), filter.cursor.as_deref(), &user_sort_key(&sort))?;
+
         let pool = self.pool.clone();
         pool::run(move || {
-            let mut conn = pool
-                .get()
-                .map_err(|e| RepoError::from(e))?;
+            let mut conn = pool.get()?;
+            let keyset = keyset(&sort);
 
-            let result = users::table
-                //.limit(filter.pagination.limit as i64)
-                //.offset(filter.pagination.offset as i64)
-                .load::<UserDiesel>(&mut conn)
-                .map_err(|e| RepoError::from(e));
+            let total = filtered(&filter)
+                .count()
+                .get_result::<i64>(&mut conn)?;
+            let mut query = filtered(&filter);
+            if let Some(condition) = paging.condition(&keyset)? {
+                query = query.filter(condition);
+            }
+            for ordering in keyset.order(paging.backward()) {
+                query = query.then_order_by(ordering);
+            }
+            let result = query
+                .limit(paging.limit())
+                .offset(paging.offset())
+                .load::<UserDiesel>(&mut conn)?;
 
-            result.map(|users| users.into_iter().map(|v| v.into()).collect())
+            Ok(paging
+                .page(result, total, &keyset, |user| keys(user, &sort))
+                .map(|user| user.into()))
         })
-        .await
-        .map_err(|e| RepoError::from(e))?
+        .await?
     }
     async fn get_by_id(&self, id: i32) -> Result<User, RepoError>{
         let pool = self.pool.clone();
@@ -110,6 +290,19 @@ impl UserRepo for UserDieselImpl {
         .await
         .map_err(|e| RepoError::from(e))?
     }
+    async fn get_existing_ids(&self, ids: Vec<i32>) -> Result<Vec<i32>, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = users::table
+                .filter(users::id.eq_any(ids))
+                .select(users::id)
+                .load::<i32>(&mut conn)?;
+            Ok(result)
+        })
+        .await?
+    }
     async fn get_by_email_or_username(&self, email_or_username: String) -> Result<User, RepoError>{
         let pool = self.pool.clone();
         pool::run(move || {
@@ -141,7 +334,9 @@ impl UserRepo for UserDieselImpl {
                 password_hash: password_hash, 
                 email: email, 
                 is_active: Some(true), 
-                created_at: Some(chrono::Utc::now().naive_utc()) 
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                is_service_account: false,
+                department: None,
             };
 
             let result = diesel::insert_into(users::table)
@@ -165,30 +360,247 @@ impl UserRepo for UserDieselImpl {
         self.get_by_id(inserted_id).await.map_err(|e| RepoError::from(e))
     
     }
-    async fn update(&self, id: i32, user: User) -> Result<User, RepoError>{
+    async fn create_with_role(&self, username: String, email: String, password_hash: String, department: Option<String>, role_id: Option<i32>) -> Result<UserCreate, RepoError>{
         let pool = self.pool.clone();
         pool::run(move || {
-            let mut conn = pool
-                .get()
-                .map_err(|e| RepoError::from(e))?;
+            let mut conn = pool.get()?;
+
+            let new_user = NewUser {
+                employee_id: None,
+                username,
+                password_hash,
+                email,
+                is_active: Some(true),
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                is_service_account: false,
+                department,
+            };
+            // set when the insert hit a unique key, the error still has to roll the grant back
+            let mut duplicate = None;
+            let result = conn.transaction::<_, RepoError, _>(|conn| {
+                let id = insert_user(conn, &new_user, role_id).map_err(|e| {
+                    duplicate = duplicate_field(&e);
+                    RepoError::from(e)
+                })?;
+                let user = users::table.find(id).first::<UserDiesel>(conn)?;
+                Ok(vec![user.into()])
+            });
+            match (result, duplicate) {
+                (Ok(created), _) => Ok(UserCreate::Created(created)),
+                (Err(_), Some(field)) => Ok(UserCreate::Duplicate(field)),
+                (Err(e), None) => Err(e),
+            }
+        })
+        .await?
+    }
+    async fn create_service_account(&self, username: String, email: String) -> Result<User, RepoError>{
+        let pool = self.pool.clone();
+        let inserted_id = pool::run(move || {
+            let mut conn = pool.get()?;
+
+            // no password hash matches this, service accounts only use API keys
+            let new_user = NewUser {
+                employee_id: None,
+                username,
+                password_hash: "!".to_string(),
+                email,
+                is_active: Some(true),
+                created_at: Some(chrono::Utc::now().naive_utc()),
+                is_service_account: true,
+                department: None,
+            };
+
+            let result = diesel::insert_into(users::table)
+                .values(&new_user)
+                .execute(&mut conn)?;
+            if result == 0 {
+                return Err(RepoError{message:"Can't inserted".to_string()});
+            }
+            let id = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+                "LAST_INSERT_ID()",
+            ))
+            .get_result::<i32>(&mut conn)?;
+            Ok(id)
+        })
+        .await??;
+
+        self.get_by_id(inserted_id).await
+    }
+    async fn create_many(&self, accounts: Vec<NewAccount>) -> Result<UserCreate, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            // set when the insert hit a unique key, the error still has to roll everything back
+            let mut duplicate = None;
+            let result = conn.transaction::<_, RepoError, _>(|conn| {
+                let now = chrono::Utc::now().naive_utc();
+                let mut ids = Vec::with_capacity(accounts.len());
+                for account in accounts {
+                    let new_user = NewUser {
+                        employee_id: account.employee_id,
+                        username: account.username,
+                        password_hash: account.password_hash,
+                        email: account.email,
+                        is_active: Some(true),
+                        created_at: Some(now),
+                        is_service_account: false,
+                        department: account.department,
+                    };
+                    let id = insert_user(conn, &new_user, account.role_id).map_err(|e| {
+                        duplicate = duplicate_field(&e);
+                        RepoError::from(e)
+                    })?;
+                    ids.push(id);
+                }
+
+                let created = users::table
+                    .filter(users::id.eq_any(&ids))
+                    .order(users::id.asc())
+                    .load::<UserDiesel>(conn)?;
+                Ok(created.into_iter().map(|user| user.into()).collect())
+            });
+            match (result, duplicate) {
+                (Ok(created), _) => Ok(UserCreate::Created(created)),
+                (Err(_), Some(field)) => Ok(UserCreate::Duplicate(field)),
+                (Err(e), None) => Err(e),
+            }
+        })
+        .await?
+    }
+    async fn update(&self, id: i32, patch: UserPatch, version: Option<i32>) -> Result<UserUpdate, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let changes = (
+                UserChangeset::from(patch),
+                users::version.eq(users::version + 1),
+                users::updated_at.eq(chrono::Utc::now().naive_utc()),
+            );
+            let result = match version {
+                Some(version) => diesel::update(users::table.find(id).filter(users::version.eq(version)))
+                    .set(changes)
+                    .execute(&mut conn),
+                None => diesel::update(users::table.find(id))
+                    .set(changes)
+                    .execute(&mut conn),
+            };
+            match result {
+                // callers load the user first, so no row means another version
+                Ok(0) => return Ok(UserUpdate::Stale),
+                Ok(_) => {}
+                Err(e) => match duplicate_field(&e) {
+                    Some(field) => return Ok(UserUpdate::Duplicate(field)),
+                    None => return Err(e.into()),
+                },
+            }
+
+            let user = users::table
+                .find(id)
+                .first::<UserDiesel>(&mut conn)?;
+            Ok(UserUpdate::Updated(user.into()))
+        })
+        .await?
+    }
+    async fn update_password(&self, id: i32, password_hash: String) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
 
             let result = diesel::update(users::table.find(id))
-                .set(users::username.eq(user.username))
-                .execute(&mut conn)
-                .map_err(|e| RepoError::from(e))?;
+                .set(users::password_hash.eq(password_hash))
+                .execute(&mut conn)?;
 
             if result == 0 {
                 return Err(RepoError{message:"Can't updated".to_string()});
             }
-            let user_update = users::table
+            Ok(())
+        })
+        .await?
+    }
+    async fn increment_failed_logins(&self, id: i32) -> Result<i32, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            conn.transaction::<_, RepoError, _>(|conn| {
+                diesel::update(users::table.find(id))
+                    .set(users::failed_login_count.eq(users::failed_login_count + 1))
+                    .execute(conn)?;
+
+                let count = users::table
+                    .find(id)
+                    .select(users::failed_login_count)
+                    .first::<i32>(conn)?;
+                Ok(count)
+            })
+        })
+        .await?
+    }
+    async fn lock(&self, id: i32, until: NaiveDateTime) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(users::table.find(id))
+                .set(users::locked_until.eq(Some(until)))
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn reset_failed_logins(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(users::table.find(id))
+                .set((
+                    users::failed_login_count.eq(0),
+                    users::locked_until.eq(None::<NaiveDateTime>),
+                ))
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn set_email_verified(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            diesel::update(users::table.find(id))
+                .set(users::email_verified.eq(true))
+                .execute(&mut conn)?;
+            Ok(())
+        })
+        .await?
+    }
+    async fn get_permissions_version(&self, id: i32) -> Result<i32, RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let version = users::table
                 .find(id)
-                .first::<UserDiesel>(&mut conn)
-                .map_err(|e| RepoError::from(e))?;
+                .select(users::permissions_version)
+                .first::<i32>(&mut conn)?;
+            Ok(version)
+        })
+        .await?
+    }
+    async fn bump_permissions_version(&self, ids: Vec<i32>) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
 
-            Ok(user_update.into())
+            diesel::update(users::table.filter(users::id.eq_any(ids)))
+                .set(users::permissions_version.eq(users::permissions_version + 1))
+                .execute(&mut conn)?;
+            Ok(())
         })
-        .await
-        .map_err(|e| RepoError::from(e))?
+        .await?
     }
     async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>{
         let pool = self.pool.clone();
@@ -198,7 +610,7 @@ impl UserRepo for UserDieselImpl {
                 .map_err(|e| RepoError::from(e))?;
 
             let result = diesel::update(users::table.find(id.clone()))
-                .set(users::is_active.eq(false))
+                .set((users::is_active.eq(false), users::version.eq(users::version + 1), users::updated_at.eq(chrono::Utc::now().naive_utc())))
                 .execute(&mut conn)
                 .map_err(|e| RepoError::from(e))?;
 
@@ -217,16 +629,30 @@ impl UserRepo for UserDieselImpl {
                 .get()
                 .map_err(|e| RepoError::from(e))?;
 
-            for id in id.clone(){
-                 diesel::update(users::table.find(id))
-                .set(users::is_active.eq(false))
+            // one statement, so either every user is deactivated or none
+            diesel::update(users::table.filter(users::id.eq_any(id.clone())))
+                .set((users::is_active.eq(false), users::version.eq(users::version + 1), users::updated_at.eq(chrono::Utc::now().naive_utc())))
                 .execute(&mut conn)
                 .map_err(|e| RepoError::from(e))?;
-            }
 
             Ok(id)
         })
         .await
         .map_err(|e| RepoError::from(e))?
     }
+    async fn reactivate(&self, id: i32) -> Result<(), RepoError>{
+        let pool = self.pool.clone();
+        pool::run(move || {
+            let mut conn = pool.get()?;
+
+            let result = diesel::update(users::table.find(id))
+                .set((users::is_active.eq(true), users::version.eq(users::version + 1), users::updated_at.eq(chrono::Utc::now().naive_utc())))
+                .execute(&mut conn)?;
+            if result == 0 {
+                return Err(RepoError{message:"Can't updated".to_string()});
+            }
+            Ok(())
+        })
+        .await?
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use crate::domain::error::RepoError;
+
+// one authorization decision, written for allowed and denied requests alike
+#[derive(Debug, Clone)]
+pub struct NewAuditLog {
+    pub user_id: i32,
+    pub api_key_id: Option<i32>,
+    pub resource: String,
+    pub action: String,
+    // `{id}` of the request path, when the route has one
+    pub resource_id: Option<String>,
+    pub allowed: bool,
+    pub reason: String,
+    pub method: String,
+    pub path: String,
+    pub ip_address: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait AuditRepo: Send + Sync {
+    async fn record(&self, entry: NewAuditLog) -> Result<(), RepoError>;
+}
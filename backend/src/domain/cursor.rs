@@ -0,0 +1,108 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use common_model::page::PageRequest;
+use serde_json::Value;
+
+use crate::domain::error::{CommonError, FieldError};
+
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+pub const MAX_PAGE_SIZE: i64 = 100;
+
+// opaque position in a sorted list: the sort key values of the row a page continues from.
+// Clients only ever get it back as next_cursor / prev_cursor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    // sort the cursor was issued for, it is rejected with any other
+    #[serde(rename = "s")]
+    pub sort: String,
+    // values of the sort columns, the id last
+    #[serde(rename = "k")]
+    pub keys: Vec<Value>,
+    // set on prev_cursor: the page before the row instead of after it
+    #[serde(rename = "b", default)]
+    pub backward: bool,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    // Err when the cursor was tampered with or issued for another sort
+    pub fn decode(cursor: &str, sort: &str) -> Result<Self, String> {
+        let malformed = || "Cursor is malformed".to_string();
+        let bytes = URL_SAFE_NO_PAD.decode(cursor.trim()).map_err(|_| malformed())?;
+        let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| malformed())?;
+        if cursor.sort != sort {
+            return Err("Cursor was issued for a different sort".to_string());
+        }
+        Ok(cursor)
+    }
+}
+
+// field errors of the paging part of a list request, `sort` as in Cursor::decode
+pub fn check_page(page_num: Option<i64>, page_size: Option<i64>, cursor: Option<&str>, sort: &str) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if page_num.is_some_and(|page_num| page_num < 1) {
+        errors.push(FieldError::new("pageNum", "out_of_range", "Page number starts at 1"));
+    }
+    if page_size.is_some_and(|page_size| !(1..=MAX_PAGE_SIZE).contains(&page_size)) {
+        errors.push(FieldError::new("pageSize", "out_of_range", format!("Page size must be between 1 and {}", MAX_PAGE_SIZE)));
+    }
+    if let Some(cursor) = cursor.filter(|cursor| !cursor.trim().is_empty())
+        && let Err(message) = Cursor::decode(cursor, sort)
+    {
+        errors.push(FieldError::new("cursor", "invalid_cursor", message));
+    }
+    errors
+}
+
+pub fn check_page_request(page: &PageRequest, sort: &str) -> Result<(), CommonError> {
+    let errors = check_page(page.page_num, page.page_size, page.cursor.as_deref(), sort);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(CommonError::validation(errors))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn cursor() -> Cursor {
+        Cursor { sort: "-created_at".to_string(), keys: vec![json!("2026-10-18T12:00:00"), json!(42)], backward: false }
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let cursor = cursor();
+        assert_eq!(Cursor::decode(&cursor.encode(), "-created_at"), Ok(cursor.clone()));
+        let backward = Cursor { backward: true, ..cursor };
+        assert_eq!(Cursor::decode(&backward.encode(), "-created_at"), Ok(backward));
+    }
+
+    #[test]
+    fn rejects_a_cursor_of_another_sort() {
+        assert_eq!(Cursor::decode(&cursor().encode(), "username"), Err("Cursor was issued for a different sort".to_string()));
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let not_json = URL_SAFE_NO_PAD.encode(b"not json");
+        for cursor in ["", "***", not_json.as_str()] {
+            assert_eq!(Cursor::decode(cursor, "-created_at"), Err("Cursor is malformed".to_string()));
+        }
+    }
+
+    #[test]
+    fn checks_page_parameters() {
+        let fields = |errors: Vec<FieldError>| errors.into_iter().map(|error| error.field).collect::<Vec<_>>();
+        assert!(check_page(Some(1), Some(MAX_PAGE_SIZE), Some(&cursor().encode()), "-created_at").is_empty());
+        // an empty cursor means the first page
+        assert!(check_page(None, None, Some(" "), "-created_at").is_empty());
+        assert_eq!(fields(check_page(Some(0), Some(0), Some("***"), "-created_at")), ["pageNum", "pageSize", "cursor"]);
+        assert_eq!(fields(check_page(None, Some(MAX_PAGE_SIZE + 1), None, "-created_at")), ["pageSize"]);
+    }
+}
@@ -4,6 +4,33 @@ use serde::Serialize;
 pub struct CommonError {
     pub message: String,
     pub code: u32,
+    // per-field validation failures, for forms to show next to their inputs
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    // stable key the frontend can translate, e.g. "too_short"
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl Into<String>) -> Self {
+        Self { field: field.to_string(), code: code.to_string(), message: message.into() }
+    }
+}
+
+impl CommonError {
+    pub fn new(message: impl Into<String>, code: u32) -> Self {
+        Self { message: message.into(), code, errors: Vec::new() }
+    }
+
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        Self { message: "Validation failed".to_string(), code: 400, errors }
+    }
 }
 
 impl std::fmt::Display for CommonError {
@@ -20,9 +47,6 @@ pub struct RepoError {
 
 impl Into<CommonError> for RepoError {
     fn into(self) -> CommonError {
-        CommonError {
-            message: self.message,
-            code: 1,
-        }
+        CommonError::new(self.message, 1)
     }
 }
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use lettre::{message::Mailbox, AsyncFileTransport, AsyncTransport, Tokio1Executor};
+
+use crate::config::MailConfig;
+use crate::domain::error::CommonError;
+
+use super::mailer::{build_message, mailbox, Email, Mailer};
+
+// logs recipient and subject of every email and, when MAIL_FILE_DIR is set, writes it there as `<id>.eml`
+pub struct FileMailer {
+    from: Mailbox,
+    dir: Option<String>,
+    log_body: bool,
+}
+
+impl FileMailer {
+    pub fn new(config: &MailConfig) -> Result<Self, CommonError> {
+        let dir = Some(config.file_dir.clone()).filter(|dir| !dir.is_empty());
+        Ok(Self { from: mailbox(&config.from)?, dir, log_body: config.log_body })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), CommonError> {
+        let message = build_message(&self.from, &email)?;
+        // bodies carry reset and verification tokens, keep them out of the log unless asked for
+        tracing::info!("Email to {}: {}", email.to, email.subject);
+        if self.log_body {
+            tracing::debug!("Email body to {}:\n{}", email.to, email.body);
+        }
+
+        if let Some(dir) = &self.dir {
+            let file_error = |e: std::io::Error| CommonError::new(format!("Can't write email: {}", e), 500);
+            tokio::fs::create_dir_all(dir).await.map_err(file_error)?;
+            AsyncFileTransport::<Tokio1Executor>::new(dir)
+                .send(message)
+                .await
+                .map_err(|e| CommonError::new(format!("Can't write email: {}", e), 500))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use lettre::{message::{header::ContentType, Mailbox}, Message};
+
+use crate::config::MailConfig;
+use crate::domain::error::CommonError;
+
+use super::{file::FileMailer, smtp::SmtpMailer};
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    // plain text
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), CommonError>;
+}
+
+// MAIL_TRANSPORT picks the implementation, "smtp" or "file" (the default, for local development)
+pub fn mailer_from_config(config: &MailConfig) -> Result<Arc<dyn Mailer>, CommonError> {
+    match config.transport.as_str() {
+        "smtp" => Ok(Arc::new(SmtpMailer::new(config)?)),
+        "file" => Ok(Arc::new(FileMailer::new(config)?)),
+        other => Err(CommonError::new(format!("Unknown mail transport {}", other), 500)),
+    }
+}
+
+pub(crate) fn mailbox(address: &str) -> Result<Mailbox, CommonError> {
+    address
+        .parse::<Mailbox>()
+        .map_err(|e| CommonError::new(format!("Invalid email address {}: {}", address, e), 400))
+}
+
+pub(crate) fn build_message(from: &Mailbox, email: &Email) -> Result<Message, CommonError> {
+    Message::builder()
+        .from(from.clone())
+        .to(mailbox(&email.to)?)
+        .subject(email.subject.clone())
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body.clone())
+        .map_err(|e| CommonError::new(format!("Can't build email: {}", e), 500))
+}
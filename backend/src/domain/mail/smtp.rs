@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use lettre::{message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+use crate::config::MailConfig;
+use crate::domain::error::CommonError;
+
+use super::mailer::{build_message, mailbox, Email, Mailer};
+
+pub struct SmtpMailer {
+    from: Mailbox,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailConfig) -> Result<Self, CommonError> {
+        let smtp_error = |e: lettre::transport::smtp::Error| CommonError::new(format!("Invalid SMTP config: {}", e), 500);
+        // "tls" connects with implicit TLS, "starttls" upgrades the connection, "none" is for local catchers only
+        let builder = match config.smtp_tls.as_str() {
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host).map_err(smtp_error)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host).map_err(smtp_error)?,
+        };
+        let mut builder = builder.port(config.smtp_port);
+        if !config.smtp_username.is_empty() {
+            builder = builder.credentials(Credentials::new(config.smtp_username.clone(), config.smtp_password.clone()));
+        }
+
+        Ok(Self { from: mailbox(&config.from)?, transport: builder.build() })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), CommonError> {
+        let message = build_message(&self.from, &email)?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| CommonError::new(format!("Can't send email: {}", e), 500))?;
+        Ok(())
+    }
+}
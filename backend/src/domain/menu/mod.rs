@@ -0,0 +1 @@
+pub mod repo;
\ No newline at end of file
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{error::RepoError, permission::repo::Type};
+
+// i18n label, keyed the way the frontend locales are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuLabel{
+    #[serde(rename = "zh_CN")]
+    pub zh_cn: String,
+    #[serde(rename = "en_US")]
+    pub en_us: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenuItem{
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub code: String,
+    pub label: MenuLabel,
+    pub icon: Option<String>,
+    pub path: String,
+    // permission needed to see the item and everything below it, None for everyone
+    pub resource: Option<String>,
+    pub action: Option<Type>,
+    pub sort_order: i32,
+}
+
+#[async_trait::async_trait]
+pub trait MenuRepo: Send + Sync {
+    // every item, ordered by sort_order
+    async fn get(&self) -> Result<Vec<MenuItem>, RepoError>;
+}
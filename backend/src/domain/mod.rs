@@ -1,5 +1,10 @@
+pub mod audit;
+pub mod cursor;
 pub mod error;
+pub mod mail;
+pub mod menu;
 pub mod permission;
 pub mod role;
 pub mod security;
+pub mod sheet;
 pub mod user;
\ No newline at end of file
@@ -1 +1,2 @@
+pub mod policy;
 pub mod repo;
\ No newline at end of file
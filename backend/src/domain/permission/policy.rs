@@ -0,0 +1,253 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+// condition a permission only applies under, stored as JSON in `permissions.policy`.
+// "users may update themselves" reads
+//   {"eq": [{"attr": "subject.id"}, {"attr": "resource.id"}]}
+// and "managers may edit their own department"
+//   {"eq": [{"attr": "subject.department"}, {"attr": "resource.department"}]}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    All(Vec<Policy>),
+    Any(Vec<Policy>),
+    Not(Box<Policy>),
+    Eq(Operand, Operand),
+    Ne(Operand, Operand),
+    // the left value is one of the values of the right list
+    In(Operand, Operand),
+}
+
+// `{"attr": "namespace.name"}` or any literal JSON value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Operand {
+    Attr { attr: String },
+    Value(Value),
+}
+
+pub const NAMESPACES: [&str; 3] = ["subject", "resource", "context"];
+
+// what a policy is evaluated against: the caller, the target of the request and the request itself
+#[derive(Debug, Clone, Default)]
+pub struct Attributes {
+    pub subject: Map<String, Value>,
+    pub resource: Map<String, Value>,
+    pub context: Map<String, Value>,
+}
+
+impl Attributes {
+    pub fn get(&self, path: &str) -> Option<&Value> {
+        let (namespace, name) = path.split_once('.')?;
+        match namespace {
+            "subject" => self.subject.get(name),
+            "resource" => self.resource.get(name),
+            "context" => self.context.get(name),
+            _ => None,
+        }
+        .filter(|value| !value.is_null())
+    }
+}
+
+impl Operand {
+    // Err names the attribute that isn't there
+    fn resolve<'a>(&'a self, attributes: &'a Attributes) -> Result<&'a Value, String> {
+        match self {
+            Operand::Attr { attr } => attributes.get(attr).ok_or_else(|| format!("{} is missing", attr)),
+            Operand::Value(value) => Ok(value),
+        }
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let Operand::Attr { attr } = self else {
+            return Ok(());
+        };
+        match attr.split_once('.') {
+            Some((namespace, name)) if NAMESPACES.contains(&namespace) && !name.is_empty() => Ok(()),
+            _ => Err(format!("Attribute {} must be one of subject.*, resource.* or context.*", attr)),
+        }
+    }
+}
+
+impl Policy {
+    // Err when an attribute the outcome depends on is missing, which callers treat as a denial
+    pub fn evaluate(&self, attributes: &Attributes) -> Result<bool, String> {
+        match self {
+            Policy::All(policies) => {
+                for policy in policies {
+                    if !policy.evaluate(attributes)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            Policy::Any(policies) => {
+                for policy in policies {
+                    if policy.evaluate(attributes)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            Policy::Not(policy) => Ok(!policy.evaluate(attributes)?),
+            Policy::Eq(left, right) => Ok(left.resolve(attributes)? == right.resolve(attributes)?),
+            Policy::Ne(left, right) => Ok(left.resolve(attributes)? != right.resolve(attributes)?),
+            Policy::In(left, right) => {
+                let value = left.resolve(attributes)?;
+                match right.resolve(attributes)? {
+                    Value::Array(values) => Ok(values.contains(value)),
+                    _ => Err(format!("{} is not a list", right)),
+                }
+            }
+        }
+    }
+
+    // attribute paths are well-formed and lists aren't empty
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Policy::All(policies) | Policy::Any(policies) => {
+                if policies.is_empty() {
+                    return Err("all and any need at least one condition".to_string());
+                }
+                policies.iter().try_for_each(Policy::check)
+            }
+            Policy::Not(policy) => policy.check(),
+            Policy::Eq(left, right) | Policy::Ne(left, right) => {
+                left.check()?;
+                right.check()
+            }
+            Policy::In(left, right) => {
+                left.check()?;
+                right.check()?;
+                if let Operand::Value(value) = right && !value.is_array() {
+                    return Err(format!("The right side of in must be a list, got {}", value));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Attr { attr } => write!(f, "{}", attr),
+            Operand::Value(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+// readable form used in decision reasons, e.g. "subject.id == resource.id"
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |policies: &[Policy], separator: &str| {
+            policies.iter().map(|policy| policy.to_string()).collect::<Vec<_>>().join(separator)
+        };
+        match self {
+            Policy::All(policies) => write!(f, "({})", join(policies, " and ")),
+            Policy::Any(policies) => write!(f, "({})", join(policies, " or ")),
+            Policy::Not(policy) => write!(f, "not {}", policy),
+            Policy::Eq(left, right) => write!(f, "{} == {}", left, right),
+            Policy::Ne(left, right) => write!(f, "{} != {}", left, right),
+            Policy::In(left, right) => write!(f, "{} in {}", left, right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn policy(value: Value) -> Policy {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn attributes() -> Attributes {
+        let mut attributes = Attributes::default();
+        attributes.subject.insert("id".to_string(), json!(1));
+        attributes.subject.insert("department".to_string(), json!("sales"));
+        attributes.resource.insert("id".to_string(), json!(1));
+        attributes.resource.insert("department".to_string(), json!("it"));
+        attributes.resource.insert("owner".to_string(), Value::Null);
+        attributes.context.insert("method".to_string(), json!("PATCH"));
+        attributes
+    }
+
+    #[test]
+    fn eq_compares_attributes_and_literals() {
+        let attributes = attributes();
+        assert_eq!(policy(json!({"eq": [{"attr": "subject.id"}, {"attr": "resource.id"}]})).evaluate(&attributes), Ok(true));
+        assert_eq!(policy(json!({"eq": [{"attr": "subject.department"}, {"attr": "resource.department"}]})).evaluate(&attributes), Ok(false));
+        assert_eq!(policy(json!({"eq": [{"attr": "context.method"}, "PATCH"]})).evaluate(&attributes), Ok(true));
+    }
+
+    #[test]
+    fn ne_is_the_opposite_of_eq() {
+        let attributes = attributes();
+        assert_eq!(policy(json!({"ne": [{"attr": "subject.id"}, {"attr": "resource.id"}]})).evaluate(&attributes), Ok(false));
+        assert_eq!(policy(json!({"ne": [{"attr": "subject.department"}, "it"]})).evaluate(&attributes), Ok(true));
+    }
+
+    #[test]
+    fn in_looks_the_value_up_in_a_list() {
+        let attributes = attributes();
+        assert_eq!(policy(json!({"in": [{"attr": "subject.department"}, ["sales", "it"]]})).evaluate(&attributes), Ok(true));
+        assert_eq!(policy(json!({"in": [{"attr": "context.method"}, ["GET", "HEAD"]]})).evaluate(&attributes), Ok(false));
+        assert!(policy(json!({"in": [{"attr": "context.method"}, {"attr": "subject.department"}]})).evaluate(&attributes).is_err());
+    }
+
+    #[test]
+    fn all_needs_every_condition() {
+        let attributes = attributes();
+        let matching = json!({"eq": [{"attr": "subject.id"}, 1]});
+        let failing = json!({"eq": [{"attr": "subject.id"}, 2]});
+        assert_eq!(policy(json!({"all": [matching, matching]})).evaluate(&attributes), Ok(true));
+        assert_eq!(policy(json!({"all": [matching, failing]})).evaluate(&attributes), Ok(false));
+    }
+
+    #[test]
+    fn any_needs_one_condition() {
+        let attributes = attributes();
+        let matching = json!({"eq": [{"attr": "subject.id"}, 1]});
+        let failing = json!({"eq": [{"attr": "subject.id"}, 2]});
+        assert_eq!(policy(json!({"any": [failing, matching]})).evaluate(&attributes), Ok(true));
+        assert_eq!(policy(json!({"any": [failing, failing]})).evaluate(&attributes), Ok(false));
+    }
+
+    #[test]
+    fn not_negates() {
+        let attributes = attributes();
+        assert_eq!(policy(json!({"not": {"eq": [{"attr": "subject.id"}, 1]}})).evaluate(&attributes), Ok(false));
+        assert_eq!(policy(json!({"not": {"eq": [{"attr": "subject.id"}, 2]}})).evaluate(&attributes), Ok(true));
+    }
+
+    #[test]
+    fn missing_attribute_denies() {
+        let attributes = attributes();
+        // null counts as missing, and `not` must not turn the missing value into a grant
+        for value in [
+            json!({"eq": [{"attr": "resource.owner"}, {"attr": "subject.id"}]}),
+            json!({"ne": [{"attr": "resource.unknown"}, 1]}),
+            json!({"not": {"eq": [{"attr": "subject.unknown"}, 1]}}),
+            json!({"in": [{"attr": "context.unknown"}, [1]]}),
+        ] {
+            assert_eq!(policy(value).evaluate(&attributes).map_err(|missing| missing.ends_with("is missing")), Err(true));
+        }
+        // an earlier match doesn't hide a missing attribute after it
+        let any = json!({"any": [{"eq": [{"attr": "subject.id"}, 2]}, {"eq": [{"attr": "subject.unknown"}, 1]}]});
+        assert!(policy(any).evaluate(&attributes).is_err());
+    }
+
+    #[test]
+    fn check_rejects_malformed_policies() {
+        assert!(policy(json!({"eq": [{"attr": "subject.id"}, {"attr": "resource.id"}]})).check().is_ok());
+        assert!(policy(json!({"eq": [{"attr": "request.id"}, 1]})).check().is_err());
+        assert!(policy(json!({"eq": [{"attr": "subject."}, 1]})).check().is_err());
+        assert!(policy(json!({"all": []})).check().is_err());
+        assert!(policy(json!({"in": [{"attr": "subject.id"}, 1]})).check().is_err());
+    }
+}
@@ -1,5 +1,7 @@
+use common_model::page::{PageData, PageRequest};
 use serde::{Deserialize, Serialize};
 use crate::domain::error::RepoError;
+use crate::domain::permission::policy::Policy;
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -7,24 +9,78 @@ pub struct Permission{
     pub id: i32,
     pub resource: String,
     pub action: Vec<Action>,
-    pub description: String
+    pub description: String,
+    // the grant only applies to requests the policy holds for, None for always
+    pub policy: Option<Policy>,
+}
+
+impl Permission {
+    pub fn allows(&self, resource: &str, action: &Type) -> bool {
+        self.resource == resource && self.action.iter().any(|a| &a.key == action)
+    }
+}
+
+// "resource:ACTION", how a single grant is written in API key scopes
+pub fn scope_key(resource: &str, action: &Type) -> String {
+    format!("{}:{}", resource, action.to_string())
 }
 
 #[async_trait::async_trait]
 pub trait PermissionRepo: Send + Sync {
     async fn get(&self) -> Result<Vec<Permission>, RepoError>;
+    // one page of the permissions by id
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Permission>, RepoError>;
+    async fn get_by_id(&self, id: i32) -> Result<Permission, RepoError>;
     async fn get_permissions_by_role_id(&self, role_id: i32)->Result<Vec<Permission>, RepoError>;
+    // roles the permission is directly assigned to
+    async fn get_role_ids(&self, id: i32) -> Result<Vec<i32>, RepoError>;
     async fn get_actions_by_ids(&self, ids: Vec<i32>)-> Result<Vec<Action>, RepoError>;
+    async fn create(&self, resource: String, description: String, action_ids: Vec<i32>, policy: Option<Policy>) -> Result<Permission, RepoError>;
+    // replaces the granted actions with `action_ids`
+    async fn update(&self, id: i32, resource: String, description: String, action_ids: Vec<i32>, policy: Option<Policy>) -> Result<Permission, RepoError>;
+    // also removes the permission from every role
+    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>;
+}
+
+#[async_trait::async_trait]
+pub trait ActionRepo: Send + Sync {
+    async fn get(&self) -> Result<Vec<Action>, RepoError>;
+    // one page of the actions by id
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Action>, RepoError>;
+    async fn get_by_id(&self, id: i32) -> Result<Action, RepoError>;
+    async fn get_by_key(&self, key: String) -> Result<Option<Action>, RepoError>;
+    async fn create(&self, key: String, description: String) -> Result<Action, RepoError>;
+    async fn update(&self, id: i32, description: String) -> Result<Action, RepoError>;
+    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>;
+    // number of permissions granting the action
+    async fn count_usage(&self, id: i32) -> Result<i64, RepoError>;
 }
 
 
 use std::str::FromStr;
+// the built-in actions, plus any key an admin added to the `actions` table.
+// Serialized as the bare key, e.g. "READ" or "EXPORT"
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "String", into = "String")]
 pub enum Type{
     CREATE,
     UPDATE,
     DELETE,
     READ,
+    Custom(String),
+}
+
+impl Type {
+    pub fn is_builtin(&self) -> bool {
+        !matches!(self, Type::Custom(_))
+    }
+}
+
+// upper case letters, digits and underscores, starting with a letter
+pub fn is_action_key(key: &str) -> bool {
+    key.len() <= 50
+        && key.starts_with(|c: char| c.is_ascii_uppercase())
+        && key.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
 }
 
 impl ToString for Type {
@@ -34,6 +90,7 @@ impl ToString for Type {
             Type::UPDATE => "UPDATE".into(),
             Type::DELETE => "DELETE".into(),
             Type::READ => "READ".into(),
+            Type::Custom(key) => key.clone(),
         }
     }
 }
@@ -46,11 +103,26 @@ impl FromStr for Type {
             "UPDATE" =>Ok(Type::UPDATE),
             "DELETE" =>Ok(Type::DELETE),
             "READ" =>Ok(Type::READ),
+            key if is_action_key(key) => Ok(Type::Custom(key.to_string())),
             _ => Err(())
         }
     }
 }
 
+impl TryFrom<String> for Type {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Type::from_str(&value).map_err(|_| format!("Invalid action key {}", value))
+    }
+}
+
+impl From<Type> for String {
+    fn from(value: Type) -> Self {
+        value.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Action{
     pub id: i32,
@@ -1,4 +1,6 @@
 
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
 use serde::{Deserialize, Serialize};
 
 use crate::domain::{error::RepoError};
@@ -9,14 +11,86 @@ pub struct Role{
     pub id: i32,
     pub name: String,
     pub description: String,
+    // members must use two-factor authentication to sign in
+    pub require_mfa: bool,
+    // permissions of the parent (and its own parents) are inherited
+    pub parent_role_id: Option<i32>,
+}
+
+// the role followed by its ancestors, nearest first. Stops at a repeated role so a cycle in the data can't loop
+pub fn role_chain(roles: &[Role], id: i32) -> Vec<Role> {
+    let mut chain: Vec<Role> = Vec::new();
+    let mut next = Some(id);
+    while let Some(current) = next {
+        if chain.iter().any(|role| role.id == current) {
+            break;
+        }
+        let Some(role) = roles.iter().find(|role| role.id == current) else {
+            break;
+        };
+        next = role.parent_role_id;
+        chain.push(role.clone());
+    }
+    chain
+}
+
+// a role held by a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleGrant{
+    #[serde(flatten)]
+    pub role: Role,
+    pub granted_at: Option<NaiveDateTime>,
+    // the grant stops counting after this, None is permanent
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleMember{
+    pub user_id: i32,
+    pub username: String,
+    pub email: String,
+    pub granted_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+// what became of a delete
+#[derive(Debug)]
+pub enum RoleDelete {
+    Deleted,
+    NotFound,
+    // number of users still holding an unexpired grant
+    Assigned(i64),
+    // number of roles inheriting from it
+    Inherited(i64),
 }
 
 #[async_trait::async_trait]
 pub trait RoleRepo: Send + Sync {
     async fn get(&self) -> Result<Vec<Role>, RepoError>;
+    // one page of the roles by id
+    async fn get_page(&self, page: PageRequest) -> Result<PageData<Role>, RepoError>;
     async fn get_by_id(&self, id: i32) -> Result<Role, RepoError>;
-    async fn create(&self, name: String, description: String) -> Result<Role, RepoError>;
-    async fn update(&self, id: i32, name: String, description: String) -> Result<Role, RepoError>;
-    async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>;
+    async fn get_by_name(&self, name: String) -> Result<Option<Role>, RepoError>;
+    async fn create(&self, name: String, description: String, parent_role_id: Option<i32>) -> Result<Role, RepoError>;
+    async fn update(&self, id: i32, name: String, description: String, parent_role_id: Option<i32>) -> Result<Role, RepoError>;
+    // only while no user holds an unexpired grant and no role inherits from it. Also drops the
+    // role's permission assignments and expired grants
+    async fn delete_by_id(&self, id: i32) -> Result<RoleDelete, RepoError>;
+    // assigning a permission the role already has is a no-op
+    async fn add_permission(&self, id: i32, permission_id: i32) -> Result<(), RepoError>;
+    async fn remove_permission(&self, id: i32, permission_id: i32) -> Result<usize, RepoError>;
+    // roles of the user's unexpired grants
     async fn get_roles_by_user_id(&self, user_id: i32) -> Result<Vec<Role>, RepoError>;
+    async fn get_grants_by_user_id(&self, user_id: i32) -> Result<Vec<RoleGrant>, RepoError>;
+    // one page of the user's unexpired grants by role id
+    async fn get_grants_page(&self, user_id: i32, page: PageRequest) -> Result<PageData<RoleGrant>, RepoError>;
+    // one page of the users holding an unexpired grant of the role, by username
+    async fn get_members(&self, id: i32, page: PageRequest) -> Result<PageData<RoleMember>, RepoError>;
+    // users with any grant of the roles, expired ones included
+    async fn get_member_ids(&self, ids: Vec<i32>) -> Result<Vec<i32>, RepoError>;
+    // granting a role the user already holds replaces its expiry
+    async fn grant(&self, user_id: i32, id: i32, expires_at: Option<NaiveDateTime>) -> Result<(), RepoError>;
+    async fn grant_to_users(&self, user_ids: Vec<i32>, id: i32, expires_at: Option<NaiveDateTime>) -> Result<(), RepoError>;
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<usize, RepoError>;
+    async fn set_require_mfa(&self, id: i32, require_mfa: bool) -> Result<Role, RepoError>;
 }
\ No newline at end of file
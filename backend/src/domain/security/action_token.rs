@@ -0,0 +1,50 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::error::RepoError;
+
+// what a single-use token allows its holder to do
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ActionPurpose {
+    PasswordReset,
+    EmailVerification,
+    // the password was right, the second factor is still missing. Opaque and kept out of the
+    // JWTs on purpose, so nothing that trusts the published keys can take it for an access token
+    MfaLogin,
+}
+
+impl ActionPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionPurpose::PasswordReset => "password_reset",
+            ActionPurpose::EmailVerification => "email_verification",
+            ActionPurpose::MfaLogin => "mfa_login",
+        }
+    }
+}
+
+impl std::str::FromStr for ActionPurpose {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "password_reset" => Ok(ActionPurpose::PasswordReset),
+            "email_verification" => Ok(ActionPurpose::EmailVerification),
+            "mfa_login" => Ok(ActionPurpose::MfaLogin),
+            _ => Err(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait ActionTokenRepo: Send + Sync {
+    // stores a new token and invalidates the user's unused ones with the same purpose
+    async fn create(&self, user_id: i32, purpose: ActionPurpose, token_hash: String, expires_at: NaiveDateTime) -> Result<(), RepoError>;
+    // user of the token without using it up, None when it is unknown, used or expired
+    async fn find(&self, token_hash: String, purpose: ActionPurpose) -> Result<Option<i32>, RepoError>;
+    // marks the token used and returns its user, None when it is unknown, used or expired
+    async fn consume(&self, token_hash: String, purpose: ActionPurpose) -> Result<Option<i32>, RepoError>;
+    // uses up a password reset token and stores the new password of its user in one transaction,
+    // None like `consume`
+    async fn consume_for_password(&self, token_hash: String, password_hash: String) -> Result<Option<i32>, RepoError>;
+}
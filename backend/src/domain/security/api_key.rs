@@ -0,0 +1,45 @@
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::error::RepoError;
+
+// every API key secret starts with this, so TokenLayer can tell it from a JWT
+pub const API_KEY_PREFIX: &str = "pat_";
+
+// row of `api_keys`, the secret itself is never stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    // first characters of the secret, to recognise a key in lists
+    pub prefix: String,
+    // "resource:ACTION" pairs, always a subset of the owner's permissions when created
+    pub scopes: Vec<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub user_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[async_trait::async_trait]
+pub trait ApiKeyRepo: Send + Sync {
+    async fn create(&self, key: NewApiKey) -> Result<ApiKey, RepoError>;
+    async fn get_by_hash(&self, key_hash: String) -> Result<Option<ApiKey>, RepoError>;
+    // one page of the keys, newest first, revoked keys included
+    async fn get_by_user_id(&self, user_id: i32, page: PageRequest) -> Result<PageData<ApiKey>, RepoError>;
+    async fn touch(&self, id: i32) -> Result<(), RepoError>;
+    // returns the number of keys revoked, 0 when `id` isn't an active key of the user
+    async fn revoke(&self, user_id: i32, id: i32) -> Result<usize, RepoError>;
+}
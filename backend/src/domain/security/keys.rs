@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters,
+    OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+
+use crate::config::{JwtConfig, JwtKeyConfig};
+use crate::domain::error::CommonError;
+
+// Ed25519 SubjectPublicKeyInfo is a fixed 12 byte header followed by the raw key
+const ED25519_SPKI_LEN: usize = 44;
+
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    // only present for the key that signs new tokens
+    pub encoding: Option<EncodingKey>,
+    pub decoding: DecodingKey,
+    pub jwk: Jwk,
+}
+
+// every key that may still verify tokens, plus the one that signs them
+pub struct KeyRing {
+    pub signing_kid: String,
+    pub keys: Vec<JwtKey>,
+}
+
+fn key_error(kid: &str, message: impl std::fmt::Display) -> CommonError {
+    CommonError::new(format!("JWT key '{}': {}", kid, message), 500)
+}
+
+impl KeyRing {
+    pub fn load(config: &JwtConfig) -> Result<Self, CommonError> {
+        if config.keys.is_empty() {
+            return Err(CommonError::new("No JWT key configured, set JWT_KEYS", 500));
+        }
+        let keys = config
+            .keys
+            .iter()
+            .map(|key| JwtKey::load(Path::new(&config.key_dir), key, key.kid == config.signing_kid))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !keys.iter().any(|key| key.kid == config.signing_kid && key.encoding.is_some()) {
+            return Err(CommonError::new(format!("Signing key '{}' is not in JWT_KEYS", config.signing_kid), 500));
+        }
+        Ok(Self { signing_kid: config.signing_kid.clone(), keys })
+    }
+
+    pub fn signing_key(&self) -> &JwtKey {
+        // load() guarantees the signing key exists
+        self.get(&self.signing_kid).expect("signing key is loaded")
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&JwtKey> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet { keys: self.keys.iter().map(|key| key.jwk.clone()).collect() }
+    }
+}
+
+impl JwtKey {
+    fn load(dir: &Path, config: &JwtKeyConfig, signing: bool) -> Result<Self, CommonError> {
+        let kid = config.kid.as_str();
+        let read = |file: String| {
+            std::fs::read(dir.join(&file)).map_err(|e| key_error(kid, format!("can't read {}: {}", file, e)))
+        };
+        let public_pem = read(format!("{}.pub.pem", kid))?;
+        let private_pem = if signing { Some(read(format!("{}.pem", kid))?) } else { None };
+
+        let (algorithm, decoding, encoding, params, key_algorithm) = match config.algorithm.as_str() {
+            "RS256" => {
+                let decoding = DecodingKey::from_rsa_pem(&public_pem).map_err(|e| key_error(kid, e))?;
+                let encoding = private_pem
+                    .map(|pem| EncodingKey::from_rsa_pem(&pem).map_err(|e| key_error(kid, e)))
+                    .transpose()?;
+                (Algorithm::RS256, decoding, encoding, rsa_params(kid, &public_pem)?, KeyAlgorithm::RS256)
+            }
+            "EdDSA" => {
+                let decoding = DecodingKey::from_ed_pem(&public_pem).map_err(|e| key_error(kid, e))?;
+                let encoding = private_pem
+                    .map(|pem| EncodingKey::from_ed_pem(&pem).map_err(|e| key_error(kid, e)))
+                    .transpose()?;
+                (Algorithm::EdDSA, decoding, encoding, ed25519_params(kid, &public_pem)?, KeyAlgorithm::EdDSA)
+            }
+            other => return Err(key_error(kid, format!("unsupported algorithm {}", other))),
+        };
+
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(kid.to_string()),
+                ..Default::default()
+            },
+            algorithm: params,
+        };
+        Ok(Self { kid: kid.to_string(), algorithm, encoding, decoding, jwk })
+    }
+}
+
+fn rsa_params(kid: &str, public_pem: &[u8]) -> Result<AlgorithmParameters, CommonError> {
+    let pem = std::str::from_utf8(public_pem).map_err(|e| key_error(kid, e))?;
+    let key = RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| key_error(kid, e))?;
+    Ok(AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+        e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+    }))
+}
+
+fn ed25519_params(kid: &str, public_pem: &[u8]) -> Result<AlgorithmParameters, CommonError> {
+    let der = pem::parse(public_pem).map_err(|e| key_error(kid, e))?;
+    let spki = der.contents();
+    if spki.len() != ED25519_SPKI_LEN {
+        return Err(key_error(kid, "not an Ed25519 public key"));
+    }
+    Ok(AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(&spki[ED25519_SPKI_LEN - 32..]),
+    }))
+}
@@ -0,0 +1,10 @@
+use chrono::NaiveDateTime;
+
+use crate::domain::error::RepoError;
+
+#[async_trait::async_trait]
+pub trait LoginAttemptRepo: Send + Sync {
+    // `user_id` is None when `identifier` matched no account
+    async fn record(&self, user_id: Option<i32>, identifier: String, ip_address: Option<String>, succeeded: bool) -> Result<(), RepoError>;
+    async fn count_failures_by_ip(&self, ip_address: String, since: NaiveDateTime) -> Result<i64, RepoError>;
+}
@@ -0,0 +1,31 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::error::RepoError;
+
+// TOTP enrollment of a user, `enabled` only once a first code was confirmed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserMfa {
+    pub user_id: i32,
+    // base32 encoded
+    pub secret: String,
+    pub enabled: bool,
+    pub created_at: NaiveDateTime,
+    pub confirmed_at: Option<NaiveDateTime>,
+    // last accepted time step, a code is never accepted twice
+    pub last_used_step: Option<i64>,
+}
+
+#[async_trait::async_trait]
+pub trait MfaRepo: Send + Sync {
+    async fn get(&self, user_id: i32) -> Result<Option<UserMfa>, RepoError>;
+    // starts a new (disabled) enrollment, replacing any previous one
+    async fn save_secret(&self, user_id: i32, secret: String) -> Result<(), RepoError>;
+    // enables the enrollment and replaces the recovery codes
+    async fn enable(&self, user_id: i32, recovery_code_hashes: Vec<String>) -> Result<(), RepoError>;
+    // false when `step` (or a later one) was already used
+    async fn use_step(&self, user_id: i32, step: i64) -> Result<bool, RepoError>;
+    // false when there is no unused code with this hash
+    async fn use_recovery_code(&self, user_id: i32, code_hash: String) -> Result<bool, RepoError>;
+    async fn delete(&self, user_id: i32) -> Result<(), RepoError>;
+}
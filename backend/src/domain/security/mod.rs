@@ -1 +1,9 @@
-pub mod repo;
\ No newline at end of file
+pub mod repo;
+pub mod token;
+pub mod keys;
+pub mod login_attempt;
+pub mod mfa;
+pub mod action_token;
+pub mod password_policy;
+pub mod password_history;
+pub mod api_key;
\ No newline at end of file
@@ -0,0 +1,9 @@
+use crate::domain::error::RepoError;
+
+#[async_trait::async_trait]
+pub trait PasswordHistoryRepo: Send + Sync {
+    // stores a hash the user just set, keeping only the `keep` most recent ones
+    async fn add(&self, user_id: i32, password_hash: String, keep: i64) -> Result<(), RepoError>;
+    // newest first
+    async fn get_recent(&self, user_id: i32, limit: i64) -> Result<Vec<String>, RepoError>;
+}
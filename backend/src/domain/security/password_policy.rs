@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use crate::config::PasswordPolicyConfig;
+use crate::domain::error::FieldError;
+
+// most used passwords from public breach lists, compared case-insensitively
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+pub struct PasswordPolicy {
+    pub config: PasswordPolicyConfig,
+    common: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn new(config: PasswordPolicyConfig) -> Self {
+        let common = COMMON_PASSWORDS
+            .lines()
+            .map(|line| line.trim().to_lowercase())
+            .filter(|line| !line.is_empty())
+            .collect();
+        Self { config, common }
+    }
+
+    // every rule `password` breaks, reported on `field`. History is checked by the caller
+    pub fn check(&self, field: &str, password: &str, username: &str, email: &str) -> Vec<FieldError> {
+        let config = &self.config;
+        let mut errors = Vec::new();
+
+        let length = password.chars().count();
+        if length < config.min_length {
+            errors.push(FieldError::new(field, "too_short", format!("Password must be at least {} characters long", config.min_length)));
+        }
+        if length > config.max_length {
+            errors.push(FieldError::new(field, "too_long", format!("Password must be at most {} characters long", config.max_length)));
+        }
+        if config.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            errors.push(FieldError::new(field, "missing_lowercase", "Password must contain a lowercase letter"));
+        }
+        if config.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            errors.push(FieldError::new(field, "missing_uppercase", "Password must contain an uppercase letter"));
+        }
+        if config.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            errors.push(FieldError::new(field, "missing_digit", "Password must contain a digit"));
+        }
+        if config.require_symbol && password.chars().all(|c| c.is_alphanumeric()) {
+            errors.push(FieldError::new(field, "missing_symbol", "Password must contain a symbol"));
+        }
+
+        let lowered = password.to_lowercase();
+        if config.reject_common && self.common.contains(&lowered) {
+            errors.push(FieldError::new(field, "too_common", "Password is too common"));
+        }
+        if config.reject_user_info && contains_user_info(&lowered, username, email) {
+            errors.push(FieldError::new(field, "contains_user_info", "Password must not contain your username or email"));
+        }
+        errors
+    }
+}
+
+fn contains_user_info(lowered: &str, username: &str, email: &str) -> bool {
+    let email = email.trim().to_lowercase();
+    let local_part = email.split('@').next().unwrap_or_default().to_string();
+    [username.trim().to_lowercase(), email, local_part]
+        .iter()
+        // very short names would match too many passwords
+        .filter(|value| value.chars().count() >= 3)
+        .any(|value| lowered.contains(value.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(policy: &PasswordPolicy, password: &str) -> Vec<String> {
+        policy.check("password", password, "jdoe", "john.doe@example.com")
+            .into_iter()
+            .map(|error| error.code)
+            .collect()
+    }
+
+    #[test]
+    fn accepts_a_password_following_every_rule() {
+        let policy = PasswordPolicy::new(PasswordPolicyConfig::default());
+        assert!(codes(&policy, "Tr0ub4dor&3x").is_empty());
+    }
+
+    #[test]
+    fn reports_every_broken_rule() {
+        let policy = PasswordPolicy::new(PasswordPolicyConfig { require_symbol: true, ..PasswordPolicyConfig::default() });
+        assert_eq!(codes(&policy, "abc"), ["too_short", "missing_uppercase", "missing_digit", "missing_symbol"]);
+        assert_eq!(codes(&policy, &format!("Aa1!{}", "x".repeat(125))), ["too_long"]);
+        assert_eq!(codes(&policy, "ABCDEFG1!"), ["missing_lowercase"]);
+    }
+
+    #[test]
+    fn length_counts_characters_not_bytes() {
+        let policy = PasswordPolicy::new(PasswordPolicyConfig { min_length: 4, ..PasswordPolicyConfig::default() });
+        assert_eq!(codes(&policy, "Ää1"), ["too_short"]);
+    }
+
+    #[test]
+    fn rejects_common_passwords_ignoring_case() {
+        let policy = PasswordPolicy::new(PasswordPolicyConfig { require_uppercase: false, require_digit: false, ..PasswordPolicyConfig::default() });
+        assert_eq!(codes(&policy, "PassWord"), ["too_common"]);
+        let policy = PasswordPolicy::new(PasswordPolicyConfig { reject_common: false, ..policy.config });
+        assert!(codes(&policy, "PassWord").is_empty());
+    }
+
+    #[test]
+    fn rejects_username_and_email() {
+        let policy = PasswordPolicy::new(PasswordPolicyConfig::default());
+        assert_eq!(codes(&policy, "Xx1-JDoe-xX"), ["contains_user_info"]);
+        assert_eq!(codes(&policy, "Xx1-john.doe-xX"), ["contains_user_info"]);
+        assert_eq!(codes(&policy, "X1john.doe@example.com"), ["contains_user_info"]);
+    }
+
+    #[test]
+    fn ignores_very_short_user_info() {
+        assert!(!contains_user_info("tr0ub4dor&3", "tr", "tr@example.com"));
+    }
+}
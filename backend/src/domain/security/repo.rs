@@ -1,27 +1,40 @@
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, encode, Header, TokenData, Validation};
 use serde::{Deserialize, Serialize};
+use rand::RngCore;
 use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 
+use crate::config::{HashConfig, TokenConfig};
+use crate::domain::security::keys::KeyRing;
 use crate::domain::error::{CommonError,RepoError};
 use crate::domain::user::repo::User;
 
-pub const EXPIRES: i64 = 24 * 60 * 60;
-
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Claims {
     pub sub: i64,
     pub exp: i64,
     pub iat: i64,
     pub email: String,
-    pub username: String
+    pub username: String,
+    // session (token family) the access token belongs to
+    #[serde(default)]
+    pub sid: String,
+    // permissions version of the user when the token was issued
+    #[serde(default)]
+    pub pv: i32,
 }
 
 impl Claims {
-    pub fn new(sub: i64, email: String, username: String) -> Self {
+    pub fn new(sub: i64, email: String, username: String, sid: String, ttl: i64) -> Self {
         let iat = chrono::Utc::now().timestamp();
-        let exp = iat + EXPIRES;
-        Self { sub, exp, iat ,email, username }
+        let exp = iat + ttl;
+        Self { sub, exp, iat ,email, username, sid, pv: 0 }
     }
 }
 
@@ -35,40 +48,114 @@ pub trait SecurityService: Send + Sync {
 
     async fn verify_hash(&self, hashed: &str, raw: &str) -> Result<bool, CommonError>;
 
+    // true when the stored hash is legacy SHA-256 or uses other Argon2 parameters than the config
+    async fn needs_rehash(&self, hashed: &str) -> bool;
+
     async fn verify_jwt(&self, token: String)-> Result<bool, CommonError>;
 
-    async fn create_jwt(&self, user: &User) -> Result<String, CommonError>;
+    async fn create_jwt(&self, user: &User, session_id: &str) -> Result<(String, Claims), CommonError>;
 
     async fn decode(&self, token: &str) -> Result<TokenData<Claims>, CommonError>;
 
     async fn encode(&self, claim: Claims) -> Result<String, CommonError>;
+
+    // public part of every key that still verifies tokens
+    async fn jwks(&self) -> JwkSet;
 }
 
 #[derive(Clone)]
 pub struct SecurityServiceImpl {
-    pub key: String,
+    pub keys: Arc<KeyRing>,
+    pub hash_config: HashConfig,
+    pub token_config: TokenConfig,
 }
 
 impl SecurityServiceImpl {
-    pub fn new() -> Self {//key: &str
-        let key = std::env::var("JWT_SECRET").unwrap_or("TEST".to_owned());
+    pub fn new(keys: KeyRing, hash_config: HashConfig, token_config: TokenConfig) -> Self {
+        Self { keys: Arc::new(keys), hash_config, token_config }
+    }
 
-        Self { key }
+    fn argon2(&self) -> Result<Argon2<'static>, CommonError> {
+        let params = Params::new(
+            self.hash_config.memory_cost,
+            self.hash_config.time_cost,
+            self.hash_config.parallelism,
+            None,
+        )
+        .map_err(|e| CommonError::new(format!("Invalid argon2 params: {}", e), 500))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
     }
 }
 
+// hashes stored before Argon2: unsalted SHA-256 hex
+fn is_legacy_hash(hashed: &str) -> bool {
+    hashed.len() == 64 && hashed.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// opaque secret handed to clients, e.g. refresh tokens
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+// also used to store issued tokens, which are looked up by this digest
+pub fn sha256_hex(value: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(value.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
 #[async_trait]
 impl SecurityService for SecurityServiceImpl {
     async fn hash(&self, value: &str) -> Result<String, CommonError> {
-        let mut hasher = Sha256::new();
-        hasher.update(value.as_bytes());
-        let result = hasher.finalize();
-        Ok(hex::encode(result))
+        let argon2 = self.argon2()?;
+        let value = value.to_owned();
+        tokio::task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(value.as_bytes(), &salt)
+                .map(|hash| hash.to_string())
+                .map_err(|e| CommonError::new(format!("Error hash password: {}", e), 500))
+        })
+        .await
+        .map_err(|e| CommonError::new(e.to_string(), 500))?
     }
 
     async fn verify_hash(&self, hashed: &str, pass: &str) -> Result<bool, CommonError> {
-        let hashed_curr = self.hash(pass).await?;
-        Ok(hashed_curr == hashed)
+        if is_legacy_hash(hashed) {
+            // constant time, so the response time doesn't tell how much of the digest matched
+            return Ok(sha256_hex(pass).as_bytes().ct_eq(hashed.to_ascii_lowercase().as_bytes()).into());
+        }
+        let hashed = hashed.to_owned();
+        let pass = pass.to_owned();
+        tokio::task::spawn_blocking(move || {
+            // no password matches a hash that doesn't parse, e.g. the "!" of accounts without one
+            let Ok(parsed) = PasswordHash::new(&hashed) else {
+                return Ok(false);
+            };
+            // parameters are read from the PHC string, so hashes with older costs still verify
+            Ok(Argon2::default().verify_password(pass.as_bytes(), &parsed).is_ok())
+        })
+        .await
+        .map_err(|e| CommonError::new(e.to_string(), 500))?
+    }
+
+    async fn needs_rehash(&self, hashed: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hashed) else {
+            return true;
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident() {
+            return true;
+        }
+        match Params::try_from(&parsed) {
+            Ok(params) => {
+                params.m_cost() != self.hash_config.memory_cost
+                    || params.t_cost() != self.hash_config.time_cost
+                    || params.p_cost() != self.hash_config.parallelism
+            }
+            Err(_) => true,
+        }
     }
 
     async fn verify_jwt(&self, token: String)-> Result<bool, CommonError>{
@@ -80,45 +167,47 @@ impl SecurityService for SecurityServiceImpl {
         }
     }
 
-    async fn create_jwt(&self, user: &User) -> Result<String, CommonError> {
-        let time_origin = chrono::Utc::now();
-        let now = time_origin.clone().timestamp();
-        let exp = now + EXPIRES;
-        let claim = Claims {
-            sub: user.id.clone() as i64,
-            exp,
-            iat: now,
-            email: user.email.clone(),
-            username: user.username.clone()
-        };
-        let token = self
-            .encode(claim.clone())
-            .await
-            .map_err(|e| e.into())?;
+    async fn create_jwt(&self, user: &User, session_id: &str) -> Result<(String, Claims), CommonError> {
+        let mut claim = Claims::new(
+            user.id as i64,
+            user.email.clone(),
+            user.username.clone(),
+            session_id.to_string(),
+            self.token_config.access_token_ttl,
+        );
+        claim.pv = user.permissions_version;
+        let token = self.encode(claim.clone()).await?;
 
-        Ok(token)
+        Ok((token, claim))
     }
 
     async fn decode(&self, token: &str) -> Result<TokenData<Claims>, CommonError> {
-        let result = decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(&self.key.as_ref()),
-            &Validation::default()); //new(Algorithm::HS256)
-        match result {
-            Ok(decode) => Ok(decode),
-            Err(e) =>Err(RepoError{message: format!("Error decode token: {}", e)}.into()),
-        }
+        let invalid = |e: String| CommonError::new(format!("Error decode token: {}", e), 401);
+        // the kid picks the key, its algorithm is pinned so a token can't choose a weaker one
+        let header = decode_header(token).map_err(|e| invalid(e.to_string()))?;
+        let key = header
+            .kid
+            .as_deref()
+            .and_then(|kid| self.keys.get(kid))
+            .ok_or_else(|| invalid("unknown key id".to_string()))?;
+
+        decode::<Claims>(token, &key.decoding, &Validation::new(key.algorithm))
+            .map_err(|e| invalid(e.to_string()))
     }
 
     async fn encode(&self, claim: Claims) -> Result<String, CommonError> {
-        let token=encode(
-            &Header::default(),
-            &claim,
-            &EncodingKey::from_secret(&self.key.as_ref()),
-        );
+        let key = self.keys.signing_key();
+        let mut header = Header::new(key.algorithm);
+        header.kid = Some(key.kid.clone());
+        let encoding = key.encoding.as_ref().ok_or_else(|| CommonError::new(format!("Key {} can't sign tokens", key.kid), 500))?;
+        let token=encode(&header, &claim, encoding);
         match token {
             Ok(token) => Ok(token),
             Err(e) => Err(RepoError{message: format!("Error encode token: {}", e)}.into()),
         }
     }
+
+    async fn jwks(&self) -> JwkSet {
+        self.keys.jwks()
+    }
 }
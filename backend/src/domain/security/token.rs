@@ -0,0 +1,107 @@
+use chrono::NaiveDateTime;
+use common_model::page::{PageData, PageRequest};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::error::RepoError;
+
+// where a token was issued from, taken from the connection and request headers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub device_id: Option<String>,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+impl TokenType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenType::Access => "access",
+            TokenType::Refresh => "refresh",
+        }
+    }
+}
+
+impl std::str::FromStr for TokenType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "access" => Ok(TokenType::Access),
+            "refresh" => Ok(TokenType::Refresh),
+            _ => Err(()),
+        }
+    }
+}
+
+// row of `tokens`, `token` holds the SHA-256 of the issued token, never the token itself.
+// every refresh token issued from one login shares the same `family_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub token: String,
+    pub token_type: TokenType,
+    pub family_id: String,
+    pub device_id: Option<String>,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenPair {
+    pub token: String,
+    pub refresh_token: String,
+    // lifetime of `token` in seconds
+    pub expires_in: i64,
+}
+
+// one login of a user, i.e. a token family that still holds a usable refresh token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub device_id: Option<String>,
+    pub device_info: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+    // true for the session the request was made with
+    pub current: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewUserToken {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub token_type: TokenType,
+    pub family_id: String,
+    pub client: ClientInfo,
+    pub expires_at: NaiveDateTime,
+}
+
+#[async_trait::async_trait]
+pub trait TokenRepo: Send + Sync {
+    async fn create(&self, token: NewUserToken) -> Result<UserToken, RepoError>;
+    async fn get_by_token(&self, token_hash: String) -> Result<UserToken, RepoError>;
+    async fn touch(&self, id: i32) -> Result<(), RepoError>;
+    async fn revoke(&self, id: i32) -> Result<(), RepoError>;
+    // false when the token was already revoked, used to rotate refresh tokens exactly once
+    async fn revoke_if_active(&self, id: i32) -> Result<bool, RepoError>;
+    async fn revoke_family(&self, family_id: String) -> Result<usize, RepoError>;
+    async fn revoke_by_user_id(&self, user_id: i32) -> Result<usize, RepoError>;
+    // one page of the live sessions, newest first
+    async fn get_sessions_by_user_id(&self, user_id: i32, page: PageRequest) -> Result<PageData<Session>, RepoError>;
+    async fn revoke_session(&self, user_id: i32, family_id: String) -> Result<usize, RepoError>;
+    // revokes every session of the user except `keep`
+    async fn revoke_other_sessions(&self, user_id: i32, keep: String) -> Result<usize, RepoError>;
+}
@@ -0,0 +1,144 @@
+use std::io::Cursor;
+
+use calamine::{Reader, Xlsx};
+use rust_xlsxwriter::Workbook;
+
+// file formats tables are imported from and exported to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SheetFormat {
+    Csv,
+    Xlsx,
+}
+
+impl SheetFormat {
+    // "csv" / "xlsx", or a file name ending in one of them
+    pub fn parse(value: &str) -> Option<Self> {
+        let extension = value.rsplit('.').next().unwrap_or(value).trim().to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(SheetFormat::Csv),
+            "xlsx" => Some(SheetFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            SheetFormat::Csv => "csv",
+            SheetFormat::Xlsx => "xlsx",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            SheetFormat::Csv => "text/csv; charset=utf-8",
+            SheetFormat::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+}
+
+// a table of text cells under a header row. Only the first worksheet of a workbook is read
+#[derive(Debug, Clone, Default)]
+pub struct Sheet {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Sheet {
+    // Err says why the file can't be read
+    pub fn read(format: SheetFormat, bytes: &[u8]) -> Result<Self, String> {
+        let mut lines = match format {
+            SheetFormat::Csv => read_csv(bytes)?,
+            SheetFormat::Xlsx => read_xlsx(bytes)?,
+        }
+        .into_iter();
+        let headers = lines.next().ok_or_else(|| "The file is empty".to_string())?;
+        // blank rows are kept so row numbers match what a spreadsheet shows
+        Ok(Sheet { headers, rows: lines.collect() })
+    }
+
+    pub fn write(&self, format: SheetFormat) -> Result<Vec<u8>, String> {
+        match format {
+            SheetFormat::Csv => {
+                let mut bytes = csv_rows(std::slice::from_ref(&self.headers))?;
+                bytes.extend(csv_rows(&self.rows)?);
+                Ok(bytes)
+            }
+            SheetFormat::Xlsx => write_xlsx(self),
+        }
+    }
+
+    // cell of `row` under the header `name`, case and spacing of the header don't matter
+    pub fn cell<'a>(&self, row: &'a [String], name: &str) -> Option<&'a str> {
+        let column = self.headers.iter().position(|header| header_key(header) == header_key(name))?;
+        row.get(column).map(String::as_str).filter(|cell| !cell.is_empty())
+    }
+
+    pub fn has_column(&self, name: &str) -> bool {
+        self.headers.iter().any(|header| header_key(header) == header_key(name))
+    }
+}
+
+// "Employee ID", "employee_id" and "employeeId" are the same column
+fn header_key(header: &str) -> String {
+    header.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// user supplied text as a cell spreadsheets show as text. A leading `'` keeps values like
+// `=HYPERLINK(...)` from being run as formulas when the export is opened
+pub fn text_cell(value: &str) -> String {
+    match value.chars().next() {
+        Some('=' | '+' | '-' | '@' | '\t' | '\r') => format!("'{}", value),
+        _ => value.to_string(),
+    }
+}
+
+// CSV lines without a header, so an export can be sent a page at a time
+pub fn csv_rows(rows: &[Vec<String>]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer.write_record(row).map_err(|e| e.to_string())?;
+    }
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+fn read_csv(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    // Excel puts a byte order mark in front of UTF-8 CSV files
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+    reader.records()
+        .map(|record| {
+            record
+                .map(|record| record.iter().map(str::to_string).collect())
+                .map_err(|e| format!("Can't read the CSV file: {}", e))
+        })
+        .collect()
+}
+
+fn read_xlsx(bytes: &[u8]) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook: Xlsx<_> = Xlsx::new(Cursor::new(bytes))
+        .map_err(|e| format!("Can't read the XLSX file: {}", e))?;
+    let range = workbook.worksheet_range_at(0)
+        .ok_or_else(|| "The workbook has no worksheet".to_string())?
+        .map_err(|e| format!("Can't read the XLSX file: {}", e))?;
+    Ok(range.rows()
+        .map(|row| row.iter().map(|cell| cell.to_string().trim().to_string()).collect())
+        .collect())
+}
+
+fn write_xlsx(sheet: &Sheet) -> Result<Vec<u8>, String> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    for (row, cells) in std::iter::once(&sheet.headers).chain(&sheet.rows).enumerate() {
+        for (column, cell) in cells.iter().enumerate() {
+            worksheet.write_string(row as u32, column as u16, cell).map_err(|e| e.to_string())?;
+        }
+    }
+    workbook.save_to_buffer().map_err(|e| e.to_string())
+}
@@ -1,12 +1,23 @@
 use serde::{Deserialize, Serialize};
 
 use crate::domain::{error::RepoError, role::repo::Role};
+use common_model::page::PageData;
 use common_model::user::{CreateUserRequest,FilterUserRequest};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct  UserIdentity {
     pub email: String,
     pub user_id: i32,
+    // row in `tokens` the request was authenticated with
+    pub token_id: i32,
+    // token family, one per login. Empty for API keys
+    pub session_id: String,
+    // set when the request was authenticated with an API key instead of a JWT
+    pub api_key_id: Option<i32>,
+    // "resource:ACTION" pairs the API key is limited to, None for JWTs
+    pub scopes: Option<Vec<String>>,
+    // `pv` claim of the access token, None for API keys
+    pub permissions_version: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,21 +25,172 @@ pub struct User {
     pub id: i32,
     pub employee_id: i32,
     pub username: String,
+    // never leaves the server, whatever response the user ends up in
+    #[serde(skip_serializing)]
     pub password_hash: String,
     pub email: String,
     pub is_active: bool,
     pub created_at: chrono::NaiveDateTime,
+    pub failed_login_count: i32,
+    // login is refused until then, even with the right password
+    pub locked_until: Option<chrono::NaiveDateTime>,
+    // set once the user followed the link sent to `email`
+    pub email_verified: bool,
+    // can't log in, only acts through API keys
+    pub is_service_account: bool,
+    // bumped by every change to the user's effective permissions
+    pub permissions_version: i32,
+    // organisational unit, compared by department-scoped policies
+    pub department: Option<String>,
+    // bumped by every edit, the ETag of the user
+    pub version: i32,
+    pub updated_at: Option<chrono::NaiveDateTime>,
     //pub roles: Vec<Role>,
 }
 
+// the fields an update changes, None leaves a column as it is
+#[derive(Debug, Clone, Default)]
+pub struct UserPatch {
+    pub username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    // Some(None) clears the column
+    pub employee_id: Option<Option<i32>>,
+    pub is_active: Option<bool>,
+    pub department: Option<Option<String>>,
+}
+
+impl UserPatch {
+    pub fn is_empty(&self) -> bool {
+        self.username.is_none() && self.email.is_none() && self.email_verified.is_none()
+            && self.employee_id.is_none() && self.is_active.is_none() && self.department.is_none()
+    }
+}
+
+// an account created together with its department and role, by an import or an administrator
+#[derive(Debug, Clone)]
+pub struct NewAccount {
+    pub username: String,
+    pub email: String,
+    // "!" matches no password, the user picks one through a reset link
+    pub password_hash: String,
+    pub employee_id: Option<i32>,
+    pub department: Option<String>,
+    pub role_id: Option<i32>,
+}
+
+// what became of a create
+#[derive(Debug)]
+pub enum UserCreate {
+    Created(Vec<User>),
+    // the username or email, named by the field, belongs to another user. Nothing was created
+    Duplicate(&'static str),
+}
+
+// what became of an update
+#[derive(Debug)]
+pub enum UserUpdate {
+    Updated(User),
+    // someone else changed the user since `version` was read
+    Stale,
+    // the username or email, named by the field, belongs to another user
+    Duplicate(&'static str),
+}
+
 #[async_trait::async_trait]
 pub trait UserRepo: Send + Sync {
-    async fn get(&self, filter: FilterUserRequest) -> Result<Vec<User>, RepoError>;
+    // one page of the users matching `filter`, with the total over all pages
+    async fn get(&self, filter: FilterUserRequest) -> Result<PageData<User>, RepoError>;
     async fn get_by_id(&self, id: i32) -> Result<User, RepoError>;
+    // the ids in `ids` that belong to a user
+    async fn get_existing_ids(&self, ids: Vec<i32>) -> Result<Vec<i32>, RepoError>;
     async fn get_by_email_or_username(&self, email_or_username: String) -> Result<User, RepoError>;
     async fn create(&self, username: String, email: String, password_hash: String) -> Result<User, RepoError>;
-    async fn update(&self, id: i32, user: User) -> Result<User, RepoError>;
+    // the user with its department and role, all of it or nothing
+    async fn create_with_role(&self, username: String, email: String, password_hash: String, department: Option<String>, role_id: Option<i32>) -> Result<UserCreate, RepoError>;
+    async fn create_service_account(&self, username: String, email: String) -> Result<User, RepoError>;
+    // every account with its role or none of them
+    async fn create_many(&self, accounts: Vec<NewAccount>) -> Result<UserCreate, RepoError>;
+    // applies `patch` and bumps the version, only if the user is still at `version` when given
+    async fn update(&self, id: i32, patch: UserPatch, version: Option<i32>) -> Result<UserUpdate, RepoError>;
+    async fn update_password(&self, id: i32, password_hash: String) -> Result<(), RepoError>;
+    // returns the new failed_login_count
+    async fn increment_failed_logins(&self, id: i32) -> Result<i32, RepoError>;
+    async fn lock(&self, id: i32, until: chrono::NaiveDateTime) -> Result<(), RepoError>;
+    // clears failed_login_count and locked_until
+    async fn reset_failed_logins(&self, id: i32) -> Result<(), RepoError>;
+    async fn set_email_verified(&self, id: i32) -> Result<(), RepoError>;
+    async fn get_permissions_version(&self, id: i32) -> Result<i32, RepoError>;
+    async fn bump_permissions_version(&self, ids: Vec<i32>) -> Result<(), RepoError>;
+    // users are never removed, only deactivated
     async fn delete_by_id(&self, id: i32) -> Result<i32, RepoError>;
     async fn delete_list_ids(&self, id: Vec<i32>) -> Result<Vec<i32>, RepoError>;
+    async fn reactivate(&self, id: i32) -> Result<(), RepoError>;
+}
+
+// columns the user list can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserSortField {
+    Id,
+    Username,
+    Email,
+    EmployeeId,
+    IsActive,
+    CreatedAt,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UserSort {
+    pub field: UserSortField,
+    pub descending: bool,
+}
+
+// "-createdAt,username" into sort keys, Err names the unknown field
+pub fn parse_user_sort(sort: &str) -> Result<Vec<UserSort>, String> {
+    sort.split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(|key| {
+            let (descending, name) = match key.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, key),
+            };
+            let field = match name {
+                "id" => UserSortField::Id,
+                "username" => UserSortField::Username,
+                "email" => UserSortField::Email,
+                "employeeId" | "employee_id" => UserSortField::EmployeeId,
+                "isActive" | "is_active" => UserSortField::IsActive,
+                "createdAt" | "created_at" => UserSortField::CreatedAt,
+                _ => return Err(format!("Can't sort users by {}", name)),
+            };
+            Ok(UserSort { field, descending })
+        })
+        .collect()
+}
+
+impl UserSortField {
+    pub fn name(self) -> &'static str {
+        match self {
+            UserSortField::Id => "id",
+            UserSortField::Username => "username",
+            UserSortField::Email => "email",
+            UserSortField::EmployeeId => "employeeId",
+            UserSortField::IsActive => "isActive",
+            UserSortField::CreatedAt => "createdAt",
+        }
+    }
+
+    // NULLs can't be compared against a cursor, lists sorted by these are only numbered
+    pub fn nullable(self) -> bool {
+        matches!(self, UserSortField::EmployeeId | UserSortField::IsActive | UserSortField::CreatedAt)
+    }
+}
+
+// canonical form of the sort, what cursors are issued for
+pub fn user_sort_key(sort: &[UserSort]) -> String {
+    sort.iter()
+        .map(|key| format!("{}{}", if key.descending { "-" } else { "" }, key.field.name()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
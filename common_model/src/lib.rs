@@ -1 +1,2 @@
+pub mod page;
 pub mod user;
\ No newline at end of file
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+// paging of a list without filters, by page number or from a cursor
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRequest{
+    // 1-based
+    pub page_num: Option<i64>,
+    pub page_size: Option<i64>,
+    // next_cursor or prev_cursor of an earlier page, takes precedence over page_num
+    pub cursor: Option<String>,
+}
+
+// one page of a list, shaped like the frontend's PageData
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageData<T>{
+    // 0 for pages fetched with a cursor
+    pub page_num: i64,
+    pub page_size: i64,
+    // rows matching the filter on all pages
+    pub total: i64,
+    pub data: Vec<T>,
+    // opaque, pass back as `cursor` to get the page after / before this one
+    #[serde(rename = "next_cursor", skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(rename = "prev_cursor", skip_serializing_if = "Option::is_none")]
+    pub prev_cursor: Option<String>,
+}
+
+impl<T> PageData<T>{
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PageData<U>{
+        PageData {
+            page_num: self.page_num,
+            page_size: self.page_size,
+            total: self.total,
+            data: self.data.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+        }
+    }
+}
@@ -1,13 +1,33 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// query of the user list, every field is optional, e.g.
+// ?search=jo&isActive=true&sort=-createdAt,username&pageNum=2&pageSize=20
+// or, continuing from a previous page, ?sort=username&cursor=...&pageSize=20
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FilterUserRequest{
-
+    // matched against username and email
+    pub search: Option<String>,
+    pub is_active: Option<bool>,
+    // users currently holding the role
+    pub role_id: Option<i32>,
+    pub employee_id: Option<i32>,
+    // inclusive bounds on created_at
+    pub created_from: Option<NaiveDateTime>,
+    pub created_to: Option<NaiveDateTime>,
+    // comma separated fields, "-" in front for descending order
+    pub sort: Option<String>,
+    // 1-based
+    pub page_num: Option<i64>,
+    pub page_size: Option<i64>,
+    // next_cursor or prev_cursor of an earlier page, takes precedence over page_num
+    pub cursor: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateUserRequest{
     pub user_name: String,
     pub email: String,
     pub password: String,
-}
\ No newline at end of file
+}